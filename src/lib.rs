@@ -1,7 +1,7 @@
-#![cfg_attr(target_arch = "wasm32", feature(stdarch_wasm_atomic_wait))]
+#![cfg_attr(target_arch = "wasm32", feature(stdarch_wasm_atomic_wait, alloc_error_hook))]
 
 // Import reusable APIs from std
-pub use std::thread::{current, sleep, Result, Thread, ThreadId};
+pub use std::thread::{current, sleep, Result};
 
 #[cfg(target_arch = "wasm32")]
 mod wasm32;
@@ -0,0 +1,185 @@
+use std::{
+    alloc::{self, Layout},
+    cell::{Cell, RefCell},
+    fmt,
+    ptr::{self, NonNull},
+    slice, str,
+};
+
+thread_local! {
+    // Arena of the thread running in this web worker, if it was spawned with `Builder::with_arena`. Pooled workers keep
+    // the region for the next thread with the same arena size.
+    static ARENA: RefCell<Option<Arena>> = const { RefCell::new(None) };
+}
+
+/// Bump allocator over a memory region private to a web worker, see [Builder::with_arena](super::Builder::with_arena).
+///
+/// Allocating only moves an offset within the region, so it never contends with other threads for the global
+/// allocator. Allocations are freed all at once when the thread completes. Destructors of values allocated in the arena
+/// are never run, so values owning other resources, e.g. a `Vec`, leak them.
+///
+/// The arena is only accessible within [with_current_arena], so references into it cannot outlive the thread.
+pub struct Arena {
+    start: NonNull<u8>,
+    capacity: usize,
+    allocated: Cell<usize>,
+}
+
+// Each allocation hands out a distinct part of the region
+#[allow(clippy::mut_from_ref)]
+impl Arena {
+    // Alignment of the region, so that most types can be allocated without padding at the start
+    const ALIGN: usize = 16;
+
+    fn new(capacity: usize) -> Self {
+        let layout = Layout::from_size_align(capacity.max(1), Self::ALIGN).expect("arena size is too large");
+        // SAFETY: the layout has a non-zero size
+        let start = NonNull::new(unsafe { alloc::alloc(layout) }).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        Self {
+            start,
+            capacity,
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of bytes allocated since the thread started, including padding for alignment.
+    pub fn allocated(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// Moves `value` into the arena, and returns a reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left for the value.
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        match self.try_alloc(value) {
+            Ok(value) => value,
+            Err(_) => panic!("arena of {} bytes is exhausted", self.capacity),
+        }
+    }
+
+    /// Moves `value` into the arena, and returns a reference to it, or returns the value if the arena has no room left
+    /// for it.
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, T> {
+        match self.alloc_layout(Layout::new::<T>()) {
+            Some(ptr) => {
+                let ptr = ptr.as_ptr() as *mut T;
+                // SAFETY: the memory is suitably aligned for `T`, and no other reference points into it
+                unsafe {
+                    ptr.write(value);
+                    Ok(&mut *ptr)
+                }
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Copies `src` into the arena, and returns a reference to the copy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left for the copy.
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        let layout = Layout::array::<T>(src.len()).expect("slice is too large");
+        let ptr = match self.alloc_layout(layout) {
+            Some(ptr) => ptr.as_ptr() as *mut T,
+            None => panic!("arena of {} bytes is exhausted", self.capacity),
+        };
+        // SAFETY: the memory is suitably aligned and sized for `src`, and no other reference points into it
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+
+    /// Copies `src` into the arena, and returns a reference to the copy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left for the copy.
+    pub fn alloc_str(&self, src: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        // SAFETY: the bytes were copied from a `str`
+        unsafe { str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    // Reserves memory for `layout`, or returns `None` if the region is exhausted
+    fn alloc_layout(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let base = self.start.as_ptr() as usize;
+        let offset = (base + self.allocated.get()).checked_next_multiple_of(layout.align())? - base;
+        let end = offset.checked_add(layout.size())?;
+        if end > self.capacity {
+            return None;
+        }
+
+        self.allocated.set(end);
+        // SAFETY: the offset lies within the region
+        Some(unsafe { NonNull::new_unchecked(self.start.as_ptr().add(offset)) })
+    }
+
+    // Frees all allocations. Requires that no references into the arena are left.
+    fn reset(&mut self) {
+        self.allocated.set(0);
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity.max(1), Self::ALIGN).unwrap();
+        // SAFETY: the region was allocated with the same layout
+        unsafe { alloc::dealloc(self.start.as_ptr(), layout) };
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("capacity", &self.capacity)
+            .field("allocated", &self.allocated.get())
+            .finish()
+    }
+}
+
+// Sets up the arena of the thread about to run in this web worker. The region kept by the previous thread on a pooled
+// worker is reused if it has the same size, and freed otherwise.
+pub(crate) fn prepare(size: Option<usize>) {
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        if arena.as_ref().map(Arena::capacity) != size {
+            // The previous region is freed before the next one is allocated
+            drop(arena.take());
+            *arena = size.map(Arena::new);
+        }
+    });
+}
+
+// Frees the allocations of the thread that completed in this web worker. A pooled worker keeps the region for the next
+// thread, and otherwise it is freed, as thread locals are not dropped when the worker closes.
+pub(crate) fn finish(pooled: bool) {
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        match arena.as_mut() {
+            Some(current) if pooled => current.reset(),
+            _ => drop(arena.take()),
+        }
+    });
+}
+
+/// Runs `f` with the [Arena] of the current thread, or returns `None` if the thread was not spawned with
+/// [Builder::with_arena](super::Builder::with_arena).
+///
+/// # Safety of the arena's lifetime
+///
+/// The arena is reset once the thread completes, and its region is reused by the next thread on a pooled web worker.
+/// Allocations must not be used after that, so the arena is only lent to `f`: references into it cannot be returned
+/// from `f`, nor sent to other threads, as the arena is neither `Send` nor `Sync`. Copy data that should outlive the
+/// call out of the arena, e.g. into the return value of `f`.
+pub fn with_current_arena<R>(f: impl FnOnce(&Arena) -> R) -> Option<R> {
+    ARENA.with(|arena| arena.borrow().as_ref().map(f))
+}
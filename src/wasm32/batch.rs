@@ -0,0 +1,93 @@
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use super::{utils, JoinHandle, Result};
+
+/// Summary of how a batch of threads actually ran, see [join_batch].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchReport {
+    peak_concurrency: usize,
+    total_tasks: usize,
+    makespan: Option<f64>,
+}
+
+impl BatchReport {
+    /// Returns the highest number of threads of the batch that were running at the same time.
+    ///
+    /// This can be lower than the number of threads, e.g. if some threads completed before others had been started.
+    pub fn peak_concurrency(&self) -> usize {
+        self.peak_concurrency
+    }
+
+    /// Returns the number of threads in the batch.
+    pub fn total_tasks(&self) -> usize {
+        self.total_tasks
+    }
+
+    /// Returns the time in milliseconds from the first thread starting until the last thread completing, or `None` if
+    /// no thread started.
+    pub fn makespan(&self) -> Option<f64> {
+        self.makespan
+    }
+}
+
+/// Waits for all threads of a batch to finish, e.g. the handles returned by
+/// [Builder::spawn_batch](super::Builder::spawn_batch), and reports how they ran.
+///
+/// The results are returned in the order of `handles`. The report is based on the [timings](JoinHandle::timings) of
+/// the threads: a thread runs from its start until its completion, or until its join handle resolved if the
+/// completion has not been recorded, e.g. because it was terminated. Threads that never started are not counted as
+/// running.
+pub async fn join_batch<T: Send + 'static>(handles: Vec<JoinHandle<T>>) -> (Vec<Result<T>>, BatchReport) {
+    let total_tasks = handles.len();
+    let mut joins: FuturesUnordered<_> = handles
+        .into_iter()
+        .enumerate()
+        .map(|(index, handle)| {
+            let state = handle.0.state.clone();
+            async move {
+                let result = handle.join_async().await;
+                (index, result, state, utils::performance_now())
+            }
+        })
+        .collect();
+
+    let mut results: Vec<Option<Result<T>>> = (0..total_tasks).map(|_| None).collect();
+    let mut intervals = Vec::new();
+    while let Some((index, result, state, joined_at)) = joins.next().await {
+        results[index] = Some(result);
+        let timings = state.timestamps.snapshot();
+        if let Some(started_at) = timings.started_at() {
+            intervals.push((started_at, timings.completed_at().unwrap_or(joined_at)));
+        }
+    }
+
+    let report = BatchReport {
+        peak_concurrency: peak_overlap(&intervals),
+        total_tasks,
+        makespan: intervals
+            .iter()
+            .map(|&(start, _)| start)
+            .reduce(f64::min)
+            .zip(intervals.iter().map(|&(_, end)| end).reduce(f64::max))
+            .map(|(start, end)| end - start),
+    };
+    (results.into_iter().map(Option::unwrap).collect(), report)
+}
+
+// Returns the highest number of overlapping intervals
+fn peak_overlap(intervals: &[(f64, f64)]) -> usize {
+    // Ends sort before starts at the same time, so that back to back intervals do not overlap
+    let mut events: Vec<(f64, i32)> = intervals
+        .iter()
+        .flat_map(|&(start, end)| [(start, 1), (end, -1)])
+        .collect();
+    events.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut running = 0;
+    let mut peak = 0;
+    for (_, change) in events {
+        running += change;
+        peak = peak.max(running);
+    }
+    peak as usize
+}
@@ -0,0 +1,152 @@
+use std::{
+    fmt, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use super::{signal::Signal, utils::SpinLockMutex};
+
+/// A token for cooperatively cancelling threads.
+///
+/// Cancellation does not interrupt a thread. Instead, the thread is expected to check [Self::is_cancelled] or wait on
+/// [Self::cancelled] and return early. Clones of a token share the same state, and cancelling a token also cancels
+/// all of its [child tokens](Self::child_token).
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+// Tokens that have been created and not dropped yet, for `shutdown`
+static LIVE_TOKENS: Mutex<Vec<Weak<Inner>>> = Mutex::new(Vec::new());
+
+struct Inner {
+    signal: Signal,
+    children: Mutex<Vec<Weak<Inner>>>,
+}
+
+impl Inner {
+    fn cancel(&self) {
+        self.signal.signal();
+
+        let children = std::mem::take(&mut *self.children.lock_spin().unwrap());
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+impl CancellationToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> Self {
+        let inner = Arc::new(Inner {
+            signal: Signal::new(),
+            children: Mutex::new(Vec::new()),
+        });
+
+        let mut live_tokens = LIVE_TOKENS.lock_spin().unwrap();
+        live_tokens.retain(|token| token.strong_count() > 0);
+        live_tokens.push(Arc::downgrade(&inner));
+
+        Self { inner }
+    }
+
+    /// Creates a token that is cancelled whenever this token is cancelled.
+    ///
+    /// Cancelling the child token does not affect its parent.
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+
+        let mut children = self.inner.children.lock_spin().unwrap();
+        if self.is_cancelled() {
+            child.cancel();
+        } else {
+            // Forget children that have been dropped in the meantime
+            children.retain(|child| child.strong_count() > 0);
+            children.push(Arc::downgrade(&child.inner));
+        }
+
+        child
+    }
+
+    /// Cancels this token and all of its children.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Returns `true` if this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.signal.is_signaled()
+    }
+
+    /// Blocks until this token is cancelled.
+    ///
+    /// Blocking waits are not allowed on the main thread, use [Self::cancelled] there instead.
+    pub fn wait(&self) {
+        self.inner.signal.wait();
+    }
+
+    /// Waits asynchronously until this token is cancelled.
+    pub async fn cancelled(&self) {
+        self.inner.signal.wait_async().await;
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Cancels all live [CancellationToken]s and terminates the threads that opted into cancellation, e.g. to clean up
+/// before the page is unloaded.
+///
+/// Threads that have a token, e.g. one set with [Builder::cancellation_token](super::Builder::cancellation_token),
+/// are terminated before it is cancelled, whether or not they check it, so joining them yields an error with a
+/// [Termination::Terminated](super::Termination::Terminated) payload unless they have returned already. Termination is
+/// the only guarantee: there is no grace period for a thread to observe its token and exit on its own. Threads that
+/// may borrow data are never terminated, see [terminate_all_workers](super::terminate_all_workers), and threads
+/// without a token are left to finish. When called from a web worker, terminating is relayed to the main thread, so
+/// the threads may observe the cancellation and return before they are terminated.
+///
+/// Also drops the closures registered on the calling thread with
+/// [register_global_closure](super::register_global_closure), and on the main thread terminates the idle workers of
+/// the [worker pool](super::Builder::pool_size). Until [restart] is called, spawning new threads fails instead of
+/// creating web workers that would be orphaned right away.
+pub fn shutdown() {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    super::drop_global_closures();
+    super::pool::drop_idle_workers();
+    // Terminate first, so that on the main thread no thread returns in reaction to the cancellation
+    super::terminate_cancellable_workers();
+    let live_tokens = std::mem::take(&mut *LIVE_TOKENS.lock_spin().unwrap());
+    for token in live_tokens.iter().filter_map(Weak::upgrade) {
+        token.cancel();
+    }
+}
+
+// Set by `shutdown` and reset by `restart`
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Allows spawning threads again after [shutdown], e.g. once the next page of a single page application has been
+/// initialized.
+pub fn restart() {
+    SHUTTING_DOWN.store(false, Ordering::Relaxed);
+}
+
+/// Fails if [shutdown] has been called and [restart] has not been called since.
+pub(crate) fn check_not_shutting_down() -> io::Result<()> {
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Other, "crate is shutting down"));
+    }
+    Ok(())
+}
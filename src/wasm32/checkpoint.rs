@@ -0,0 +1,82 @@
+use std::{
+    any::Any,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use super::{utils::SpinLockMutex, CURRENT_STATE};
+
+/// A shared slot for partial results of a thread, which survive the thread panicking or being terminated.
+///
+/// Attach a checkpoint to a thread with [Builder::with_checkpoint](super::Builder::with_checkpoint), save partial
+/// results from within the thread, and read the last one through any clone of the checkpoint or with
+/// [JoinHandle::last_checkpoint](super::JoinHandle::last_checkpoint).
+pub struct Checkpoint<C> {
+    slot: Arc<Mutex<Option<C>>>,
+}
+
+impl<C> Checkpoint<C> {
+    /// Creates an empty checkpoint.
+    pub fn new() -> Self {
+        Self {
+            slot: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Replaces the saved value.
+    pub fn save(&self, value: C) {
+        // Drop the previous value outside of the lock, so that readers are not held up by its destructor
+        let previous = self.slot.lock_spin().unwrap().replace(value);
+        drop(previous);
+    }
+
+    /// Removes and returns the saved value.
+    pub fn take(&self) -> Option<C> {
+        self.slot.lock_spin().unwrap().take()
+    }
+
+    /// Returns a copy of the saved value.
+    pub fn get(&self) -> Option<C>
+    where
+        C: Clone,
+    {
+        self.slot.lock_spin().unwrap().clone()
+    }
+}
+
+impl<C: Send + 'static> Checkpoint<C> {
+    /// Returns the checkpoint attached to the current thread, if it has one of type `C`.
+    pub fn current() -> Option<Self> {
+        let slot = CURRENT_STATE.with(|state| state.borrow().as_ref()?.checkpoint.clone())?;
+        slot.downcast().ok().map(|slot| Self { slot })
+    }
+
+    pub(crate) fn erase(&self) -> Arc<dyn Any + Send + Sync> {
+        self.slot.clone()
+    }
+}
+
+impl<C> Clone for Checkpoint<C> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<C> Default for Checkpoint<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> fmt::Debug for Checkpoint<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Checkpoint").finish_non_exhaustive()
+    }
+}
+
+// Reads a checkpoint that has been erased by `Checkpoint::erase`
+pub(crate) fn read_erased<C: Clone + 'static>(slot: &(dyn Any + Send + Sync)) -> Option<C> {
+    slot.downcast_ref::<Mutex<Option<C>>>()?.lock_spin().unwrap().clone()
+}
@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use wasm_bindgen::JsValue;
+
+use super::Builder;
+
+/// Type of the web worker script, see [Builder::worker_type].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorkerKind {
+    /// A classic script, which loads other scripts with `importScripts`.
+    Classic,
+    /// An ES module, which loads other modules with `import`.
+    Module,
+}
+
+/// Credentials sent when the web worker script is fetched, see [Builder::credentials].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorkerCredentials {
+    /// Never send credentials.
+    Omit,
+    /// Send credentials to the same origin only.
+    SameOrigin,
+    /// Always send credentials, including cross-origin.
+    Include,
+}
+
+/// Worker settings that can be applied to a [Builder] in one go with [Builder::from_config].
+///
+/// Fields left as `None` keep the value of the builder. With the `serde` feature, the config can be serialized, and
+/// missing fields are deserialized as `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WorkerConfig {
+    /// See [Builder::name].
+    pub name: Option<String>,
+    /// See [Builder::prefix].
+    pub prefix: Option<String>,
+    /// See [Builder::worker_type].
+    pub worker_type: Option<WorkerKind>,
+    /// See [Builder::credentials].
+    pub credentials: Option<WorkerCredentials>,
+    /// See [Builder::stack_size].
+    pub stack_size: Option<usize>,
+    /// See [Builder::worker_script_url].
+    pub worker_script_url: Option<String>,
+    /// See [Builder::wasm_bindgen_shim_url].
+    pub wasm_bindgen_shim_url: Option<String>,
+}
+
+impl Builder {
+    /// Creates a builder inheriting global configuration options set by [Self::set_default], with the settings of
+    /// `config` applied.
+    ///
+    /// The builder can be configured further with its methods.
+    pub fn from_config(config: WorkerConfig) -> Builder {
+        let WorkerConfig {
+            name,
+            prefix,
+            worker_type,
+            credentials,
+            stack_size,
+            worker_script_url,
+            wasm_bindgen_shim_url,
+        } = config;
+
+        let mut builder = Builder::new();
+        if let Some(name) = name {
+            builder = builder.name(name);
+        }
+        if let Some(prefix) = prefix {
+            builder = builder.prefix(prefix);
+        }
+        if let Some(worker_type) = worker_type {
+            builder = builder.worker_type(worker_type);
+        }
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+        if let Some(stack_size) = stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        if let Some(url) = worker_script_url {
+            builder = builder.worker_script_url(url);
+        }
+        if let Some(url) = wasm_bindgen_shim_url {
+            builder = builder.wasm_bindgen_shim_url(url);
+        }
+        builder
+    }
+}
+
+/// Read-only view of the configuration a thread was spawned with, see
+/// [current_worker_config](super::current_worker_config).
+///
+/// Only plain settings are included. Closures, e.g. [critical cleanups](Builder::critical_cleanup), and JS values
+/// cannot be shared with the web worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WorkerConfigView {
+    pub(crate) name: Option<String>,
+    pub(crate) prefix: Option<String>,
+    pub(crate) stack_size: Option<usize>,
+    pub(crate) tag: Option<u64>,
+    pub(crate) worker_index: Option<usize>,
+    pub(crate) max_lifetime: Option<Duration>,
+    pub(crate) init_extra: Option<String>,
+    pub(crate) pool_size: Option<usize>,
+    pub(crate) arena_size: Option<usize>,
+    pub(crate) spawn_depth: usize,
+}
+
+impl WorkerConfigView {
+    /// Returns the name set with [Builder::name].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the prefix set with [Builder::prefix].
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// Returns the stack size set with [Builder::stack_size].
+    pub fn stack_size(&self) -> Option<usize> {
+        self.stack_size
+    }
+
+    /// Returns the tag set with [Builder::tag].
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+
+    /// Returns the index set with [Builder::worker_index].
+    pub fn worker_index(&self) -> Option<usize> {
+        self.worker_index
+    }
+
+    /// Returns the lifetime set with [Builder::max_lifetime], including the deadline of [Builder::soft_deadline].
+    ///
+    /// This is `None` for threads that are never terminated, see [Builder::max_lifetime].
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    /// Returns the pool size set with [Builder::pool_size].
+    pub fn pool_size(&self) -> Option<usize> {
+        self.pool_size
+    }
+
+    /// Returns the arena size set with [Builder::with_arena].
+    pub fn arena_size(&self) -> Option<usize> {
+        self.arena_size
+    }
+
+    /// Returns how deeply the thread is nested, which is 1 for threads spawned from the main thread, see
+    /// [set_max_spawn_depth](super::set_max_spawn_depth).
+    pub fn spawn_depth(&self) -> usize {
+        self.spawn_depth
+    }
+
+    /// Returns a copy of the value set with [Builder::init_extra].
+    pub fn init_extra(&self) -> Option<JsValue> {
+        let extra = self.init_extra.as_deref()?;
+        Some(js_sys::JSON::parse(extra).expect("init extra value was serialized as JSON"))
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use super::{utils::SpinLockMutex, ThreadState, CURRENT_STATE};
+
+/// Error payload of a blocking join that would have deadlocked, see the `deadlock_detection` feature.
+///
+/// The join that closes a cycle of threads joining each other fails with a boxed `DeadlockDetected`, which breaks the
+/// cycle. The joined thread keeps running, and the other joins of the cycle complete once the failed joiner finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlockDetected;
+
+impl fmt::Display for DeadlockDetected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadlock detected while joining thread")
+    }
+}
+
+impl std::error::Error for DeadlockDetected {}
+
+// Wait-for graph of threads blocked in `join`, from the joining thread to the joined thread. Threads are identified by
+// the address of their state, which stays alive for as long as the edge exists.
+static WAITING_FOR: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+// Removes the edge of a blocked join once it returns
+pub(crate) struct WaitGuard(Option<usize>);
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        if let Some(joiner) = self.0 {
+            WAITING_FOR.lock_spin().unwrap().remove(&joiner);
+        }
+    }
+}
+
+fn key(state: &Arc<ThreadState>) -> usize {
+    Arc::as_ptr(state) as usize
+}
+
+/// Records that the current thread is about to block on joining `target`, failing if that would close a cycle.
+pub(crate) fn begin_wait(target: &Arc<ThreadState>) -> Result<WaitGuard, DeadlockDetected> {
+    // Threads not spawned by this crate cannot be joined by others, so they cannot be part of a cycle
+    let joiner = match CURRENT_STATE.with(|state| state.borrow().as_ref().map(key)) {
+        Some(joiner) => joiner,
+        None => return Ok(WaitGuard(None)),
+    };
+
+    let mut waiting_for = WAITING_FOR.lock_spin().unwrap();
+    let mut next = Some(key(target));
+    while let Some(thread) = next {
+        if thread == joiner {
+            return Err(DeadlockDetected);
+        }
+        next = waiting_for.get(&thread).copied();
+    }
+    waiting_for.insert(joiner, key(target));
+
+    Ok(WaitGuard(Some(joiner)))
+}
@@ -0,0 +1,138 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+
+use super::utils::SpinLockMutex;
+
+/// Delivers typed events published by any thread to the subscribers of their type.
+///
+/// The bus is cheap to clone, and clones share their subscribers, so it can be moved into workers that publish
+/// events while the main thread or other workers subscribe. Each subscriber receives a copy of every event of its type
+/// published after it subscribed, in the order they were published by each thread. Dropping an [EventSubscription]
+/// unsubscribes it.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    inner: Arc<Inner>,
+}
+
+// Senders of the subscribers by event type, each an `UnboundedSender<E>` for the event type `E`
+type Subscribers = HashMap<TypeId, Vec<(u64, Box<dyn Any + Send>)>>;
+
+#[derive(Default)]
+struct Inner {
+    subscribers: Mutex<Subscribers>,
+    next_id: AtomicU64,
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes `event` to all current subscribers of its type, and returns how many received it.
+    ///
+    /// Events without subscribers are dropped.
+    pub fn publish<E: Clone + Send + 'static>(&self, event: E) -> usize {
+        let mut subscribers = self.inner.subscribers.lock_spin().unwrap();
+        let senders = match subscribers.get_mut(&TypeId::of::<E>()) {
+            Some(senders) => senders,
+            None => return 0,
+        };
+
+        // Subscribers that have been dropped concurrently are pruned on the way
+        senders.retain(|(_, sender)| {
+            let sender = sender.downcast_ref::<UnboundedSender<E>>().unwrap();
+            sender.unbounded_send(event.clone()).is_ok()
+        });
+        senders.len()
+    }
+
+    /// Subscribes to the events of type `E`.
+    pub fn subscribe<E: Send + 'static>(&self) -> EventSubscription<E> {
+        let (sender, receiver) = mpsc::unbounded::<E>();
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .subscribers
+            .lock_spin()
+            .unwrap()
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push((id, Box::new(sender)));
+
+        EventSubscription {
+            bus: self.inner.clone(),
+            id,
+            receiver,
+        }
+    }
+
+    /// Returns the number of current subscribers to the events of type `E`.
+    pub fn subscriber_count<E: 'static>(&self) -> usize {
+        self.inner
+            .subscribers
+            .lock_spin()
+            .unwrap()
+            .get(&TypeId::of::<E>())
+            .map_or(0, Vec::len)
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers: usize = self.inner.subscribers.lock_spin().unwrap().values().map(Vec::len).sum();
+        f.debug_struct("EventBus")
+            .field("subscribers", &subscribers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stream of the events of type `E` published on an [EventBus], see [EventBus::subscribe].
+///
+/// The stream never ends on its own. Dropping it unsubscribes from the bus.
+pub struct EventSubscription<E: 'static> {
+    bus: Arc<Inner>,
+    id: u64,
+    receiver: UnboundedReceiver<E>,
+}
+
+impl<E: 'static> Stream for EventSubscription<E> {
+    type Item = E;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<E>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl<E: 'static> Drop for EventSubscription<E> {
+    fn drop(&mut self) {
+        let mut subscribers = self.bus.subscribers.lock_spin().unwrap();
+        if let Some(senders) = subscribers.get_mut(&TypeId::of::<E>()) {
+            senders.retain(|(id, _)| *id != self.id);
+            if senders.is_empty() {
+                subscribers.remove(&TypeId::of::<E>());
+            }
+        }
+    }
+}
+
+impl<E: 'static> fmt::Debug for EventSubscription<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSubscription")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
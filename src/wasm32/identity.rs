@@ -0,0 +1,81 @@
+use std::{
+    fmt,
+    num::NonZeroU64,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// A unique identifier for a thread spawned by this crate, see [Thread::id].
+///
+/// Unlike [std::thread::ThreadId], which this crate cannot construct for its threads, the id is assigned when the
+/// thread is spawned, before its web worker exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(NonZeroU64);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+impl ThreadId {
+    fn next() -> Self {
+        Self(NonZeroU64::new(NEXT_ID.fetch_add(1, Ordering::Relaxed)).unwrap())
+    }
+
+    // Whether `id` has been assigned to a thread spawned by this instance of the module
+    pub(crate) fn was_issued(id: u64) -> bool {
+        id != 0 && id < NEXT_ID.load(Ordering::Relaxed)
+    }
+
+    /// Returns the numeric value of this identifier.
+    pub fn as_u64(&self) -> NonZeroU64 {
+        self.0
+    }
+}
+
+/// A handle to a thread spawned by this crate, see [JoinHandle::thread](super::JoinHandle::thread).
+///
+/// This replaces [std::thread::Thread], which cannot be constructed for web workers. It is cheap to clone and can be
+/// sent to other threads.
+#[derive(Clone)]
+pub struct Thread {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    id: ThreadId,
+    name: Option<String>,
+}
+
+impl Thread {
+    pub(crate) fn new(name: Option<String>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                id: ThreadId::next(),
+                name,
+            }),
+        }
+    }
+
+    /// Gets the thread's unique identifier.
+    pub fn id(&self) -> ThreadId {
+        self.inner.id
+    }
+
+    /// Gets the thread's name.
+    ///
+    /// This is the name the web worker is created with: the [name](super::Builder::name), prefixed with the
+    /// [prefix](super::Builder::prefix) if both are set, or the prefix followed by a random number if only the prefix
+    /// is set.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name.as_deref()
+    }
+}
+
+impl fmt::Debug for Thread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Thread")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,92 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use wasm_bindgen::prelude::*;
+
+use super::{pool, utils, Builder, Termination, Transfer, WebWorkerContext, WorkerCredentials};
+
+thread_local! {
+    // Fetches and verifies a script, see `js/verify_integrity.js`
+    static VERIFY: js_sys::Function = js_sys::eval(include_str!("js/verify_integrity.js")).unwrap().unchecked_into();
+    // Blob URLs of verified scripts, keyed by URL and integrity metadata, so that each script is only fetched once.
+    // Only used on the main thread.
+    static VERIFIED: RefCell<BTreeMap<(String, String), String>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+impl Builder {
+    // Spawns the thread once the script the worker is created from has been verified against `integrity`: the custom
+    // worker script if there is one, and otherwise the `wasm_bindgen` shim imported by the bundled script. If the
+    // script cannot be verified, the thread fails with `Termination::IntegrityCheckFailed`.
+    pub(super) unsafe fn spawn_verified(mut self, integrity: String, ctx: WebWorkerContext, values: Transfer) {
+        let url = match (&self.worker_script_url, &self.wasm_bindgen_shim_url) {
+            (Some(url), _) | (None, Some(url)) => url.clone(),
+            // The shim URL is resolved when spawning
+            (None, None) => unreachable!(),
+        };
+
+        let key = (url, integrity);
+        if let Some(verified) = VERIFIED.with(|verified| verified.borrow().get(&key).cloned()) {
+            self.use_verified_script(verified);
+            self.spawn_for_context(ctx, values);
+            return;
+        }
+
+        let credentials = match self.credentials {
+            Some(WorkerCredentials::Omit) => JsValue::from("omit"),
+            Some(WorkerCredentials::SameOrigin) => JsValue::from("same-origin"),
+            Some(WorkerCredentials::Include) => JsValue::from("include"),
+            None => JsValue::UNDEFINED,
+        };
+        let promise: js_sys::Promise = VERIFY
+            .with(|verify| {
+                verify.call3(
+                    &JsValue::UNDEFINED,
+                    &key.0.as_str().into(),
+                    &key.1.as_str().into(),
+                    &credentials,
+                )
+            })
+            .unwrap()
+            .unchecked_into();
+
+        let on_settled = Closure::once_into_js(move |settled: js_sys::Array| {
+            let settled = settled.get(0);
+            let status = js_sys::Reflect::get(&settled, &"status".into()).unwrap_or(JsValue::UNDEFINED);
+            if status.as_string().as_deref() == Some("fulfilled") {
+                let verified = js_sys::Reflect::get(&settled, &"value".into())
+                    .unwrap()
+                    .as_string()
+                    .unwrap();
+                VERIFIED.with(|cache| cache.borrow_mut().insert(key, verified.clone()));
+                self.use_verified_script(verified);
+                self.spawn_for_context(ctx, values);
+            } else {
+                let reason = js_sys::Reflect::get(&settled, &"reason".into()).unwrap_or(JsValue::UNDEFINED);
+                let reason = match reason.dyn_ref::<js_sys::Error>() {
+                    Some(error) => String::from(error.message()),
+                    None => format!("{:?}", reason),
+                };
+                utils::console_warn(&format!(
+                    "wasm_thread: refusing to spawn a web worker from an unverified script: {}",
+                    reason
+                ));
+                // The thread never runs. It is terminated before its context is dropped, so that a scope counts it as
+                // failed.
+                ctx.state.try_terminate(Termination::IntegrityCheckFailed);
+                pool::settle(&ctx.state);
+                drop(ctx);
+            }
+        });
+        // Unlike `then` with separate handlers, the callback is called exactly once, which frees it
+        utils::then_with_function(
+            &js_sys::Promise::all_settled(&js_sys::Array::of1(&promise)),
+            &on_settled,
+        );
+    }
+
+    fn use_verified_script(&mut self, verified: String) {
+        match self.worker_script_url {
+            Some(_) => self.worker_script_url = Some(verified),
+            None => self.wasm_bindgen_shim_url = Some(verified),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver},
+    executor::block_on,
+    Stream, StreamExt,
+};
+
+use super::{signal::Signal, Builder, JoinHandle};
+
+pub(crate) fn spawn<F, Fut, T>(
+    builder: Builder,
+    interval: Duration,
+    mut f: F,
+) -> std::io::Result<(IntervalStream<T>, StopHandle)>
+where
+    F: FnMut() -> Fut,
+    F: Send + 'static,
+    Fut: Future<Output = T>,
+    T: Send + 'static,
+{
+    let (sender, receiver) = mpsc::unbounded();
+    let stop = Arc::new(Signal::new());
+    let their_stop = stop.clone();
+
+    let thread = builder.spawn(move || {
+        block_on(async move {
+            while !their_stop.is_signaled() {
+                // The stream has been dropped, so nobody is interested in further results
+                if sender.unbounded_send(f().await).is_err() {
+                    break;
+                }
+                if their_stop.wait_timeout(interval) {
+                    break;
+                }
+            }
+        })
+    })?;
+
+    Ok((IntervalStream { receiver }, StopHandle { stop, thread }))
+}
+
+/// Stream of the results of a thread spawned with [Builder::spawn_interval].
+///
+/// The stream ends once the thread has stopped. Dropping it stops the thread after its next run.
+pub struct IntervalStream<T> {
+    receiver: UnboundedReceiver<T>,
+}
+
+impl<T> Stream for IntervalStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl<T> fmt::Debug for IntervalStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntervalStream").finish_non_exhaustive()
+    }
+}
+
+/// Stops a thread spawned with [Builder::spawn_interval].
+///
+/// Dropping the handle does not stop the thread, which keeps running until its [IntervalStream] is dropped.
+pub struct StopHandle {
+    stop: Arc<Signal>,
+    thread: JoinHandle<()>,
+}
+
+impl StopHandle {
+    /// Stops the thread, returning a handle to it.
+    ///
+    /// A run in progress is completed and its result is still delivered, but the thread does not wait for the next
+    /// interval, after which the stream ends.
+    pub fn stop(self) -> JoinHandle<()> {
+        self.stop.signal();
+        self.thread
+    }
+}
+
+impl fmt::Debug for StopHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StopHandle")
+            .field("thread", &self.thread)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,90 @@
+use std::{any::Any, fmt};
+
+#[cfg(feature = "deadlock_detection")]
+use super::DeadlockDetected;
+use super::{JoinInterrupted, Termination};
+
+/// Why joining a thread failed, see [JoinHandle::join_detailed](super::JoinHandle::join_detailed).
+///
+/// Unlike the error of [JoinHandle::join](super::JoinHandle::join), which is an opaque payload compatible with
+/// [std::thread::Result], this tells the ways a thread can fail apart.
+#[non_exhaustive]
+pub enum JoinError {
+    /// The thread panicked, with the panic payload. With `panic=abort`, the default on wasm32, panics trap instead.
+    Panicked(Box<dyn Any + Send + 'static>),
+    /// The thread was aborted with [JoinHandle::abort](super::JoinHandle::abort) or
+    /// [try_join_all](super::try_join_all), or terminated by [terminate_all_workers](super::terminate_all_workers).
+    Aborted,
+    /// The web worker failed with an uncaught error, e.g. because the thread panicked with `panic=abort`.
+    Trapped,
+    /// The thread did not start or complete in time, see [Builder::startup_timeout](super::Builder::startup_timeout)
+    /// and [Builder::max_lifetime](super::Builder::max_lifetime).
+    TimedOut,
+    /// The thread trapped because an allocation failed.
+    OutOfMemory,
+    /// The worker script failed its integrity check, see
+    /// [Builder::script_integrity](super::Builder::script_integrity).
+    IntegrityCheckFailed,
+    /// The join was interrupted with a [JoinInterrupter](super::JoinInterrupter), and the thread keeps running.
+    Interrupted,
+    /// The blocking join would have deadlocked, and the thread keeps running.
+    #[cfg(feature = "deadlock_detection")]
+    Deadlocked,
+}
+
+impl JoinError {
+    // Classifies the error payload of a join
+    pub(crate) fn from_payload(payload: Box<dyn Any + Send + 'static>) -> Self {
+        if let Some(termination) = payload.downcast_ref::<Termination>() {
+            return match termination {
+                Termination::Aborted | Termination::Terminated => Self::Aborted,
+                Termination::Trapped => Self::Trapped,
+                Termination::LifetimeExceeded | Termination::StartupTimedOut => Self::TimedOut,
+                Termination::OutOfMemory => Self::OutOfMemory,
+                Termination::IntegrityCheckFailed => Self::IntegrityCheckFailed,
+            };
+        }
+        if payload.is::<JoinInterrupted>() {
+            return Self::Interrupted;
+        }
+        #[cfg(feature = "deadlock_detection")]
+        if payload.is::<DeadlockDetected>() {
+            return Self::Deadlocked;
+        }
+        Self::Panicked(payload)
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(payload) => f.debug_tuple("Panicked").field(payload).finish(),
+            Self::Aborted => f.write_str("Aborted"),
+            Self::Trapped => f.write_str("Trapped"),
+            Self::TimedOut => f.write_str("TimedOut"),
+            Self::OutOfMemory => f.write_str("OutOfMemory"),
+            Self::IntegrityCheckFailed => f.write_str("IntegrityCheckFailed"),
+            Self::Interrupted => f.write_str("Interrupted"),
+            #[cfg(feature = "deadlock_detection")]
+            Self::Deadlocked => f.write_str("Deadlocked"),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(_) => f.write_str("thread panicked"),
+            Self::Aborted => f.write_str("thread was aborted"),
+            Self::Trapped => f.write_str("thread trapped"),
+            Self::TimedOut => f.write_str("thread timed out"),
+            Self::OutOfMemory => f.write_str("thread ran out of memory"),
+            Self::IntegrityCheckFailed => f.write_str("worker script failed its integrity check"),
+            Self::Interrupted => f.write_str("join was interrupted"),
+            #[cfg(feature = "deadlock_detection")]
+            Self::Deadlocked => f.write_str("deadlock detected while joining thread"),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
@@ -0,0 +1,13 @@
+// Transferring a stream to a message port fails in browsers that do not support transferable streams
+(() => {
+    try {
+        const stream = new ReadableStream();
+        const channel = new MessageChannel();
+        channel.port1.postMessage(stream, [stream]);
+        channel.port1.close();
+        channel.port2.close();
+        return true;
+    } catch (e) {
+        return false;
+    }
+})()
@@ -0,0 +1,34 @@
+// Fetches a script and checks it against Subresource Integrity metadata, e.g. `sha384-<base64 digest>`. Resolves with a
+// blob URL of the verified content, which cannot change afterwards, and rejects if no listed hash matches.
+(async (url, integrity, credentials) => {
+    const algorithms = { sha256: "SHA-256", sha384: "SHA-384", sha512: "SHA-512" };
+    const hashes = integrity
+        .trim()
+        .split(/\s+/)
+        .map(entry => {
+            const separator = entry.indexOf("-");
+            return {
+                algorithm: separator > 0 ? algorithms[entry.slice(0, separator)] : undefined,
+                // Options after `?` are reserved and ignored
+                digest: entry.slice(separator + 1).split("?")[0],
+            };
+        })
+        .filter(hash => hash.algorithm !== undefined);
+    if (hashes.length === 0) {
+        throw new Error(`unsupported integrity metadata "${integrity}"`);
+    }
+
+    const response = await fetch(url, { credentials });
+    if (!response.ok) {
+        throw new Error(`failed to fetch ${url}: ${response.status} ${response.statusText}`);
+    }
+    const content = await response.arrayBuffer();
+
+    for (const { algorithm, digest } of hashes) {
+        const actual = new Uint8Array(await crypto.subtle.digest(algorithm, content));
+        if (btoa(String.fromCharCode(...actual)) === digest) {
+            return URL.createObjectURL(new Blob([content], { type: "text/javascript" }));
+        }
+    }
+    throw new Error(`${url} does not match its integrity metadata "${integrity}"`);
+})
@@ -1,11 +1,69 @@
 // synchronously, using the browser, import wasm_bindgen shim JS scripts
 import init, {wasm_thread_entry_point} from "WASM_BINDGEN_SHIM_URL";
 
+// Makes the values sent along with a job available to the thread before it runs. Pooled workers run several jobs, so the
+// values of the previous job are replaced or cleared.
+function prepare({ name, extra, stream, ports }, pooled) {
+    // Name the global scope, so that devtools can tell the worker apart even if the worker options were not applied.
+    // `self.name` is read-only, so it is shadowed by an own property instead, which pooled workers replace for each job.
+    if (pooled) {
+        Object.defineProperty(self, "name", { value: name === undefined ? "" : name, configurable: true });
+    } else if (name !== undefined && self.name !== name) {
+        Object.defineProperty(self, "name", { value: name });
+    }
+
+    // Make the extra value set with `Builder::init_extra` available to glue code and the thread
+    if (extra !== undefined) {
+        self.wasm_thread_init_extra = JSON.parse(extra);
+    } else {
+        delete self.wasm_thread_init_extra;
+    }
+
+    // Hand the stream transferred by `Builder::spawn_with_stream` over to the thread
+    if (stream !== undefined) {
+        self.wasm_thread_stream = stream;
+    } else {
+        delete self.wasm_thread_stream;
+    }
+
+    // Hand the ports transferred by `Builder::spawn_with_ports` over to the thread
+    if (ports !== undefined) {
+        self.wasm_thread_ports = ports;
+    } else {
+        delete self.wasm_thread_ports;
+    }
+}
+
+// Enters rust code by calling the entry point defined in `lib.rs`, which executes the closure defined by the work
+// context. Returns `false` if the thread trapped.
+function run(entry_point, work) {
+    try {
+        entry_point(work);
+        return true;
+    } catch (err) {
+        // The thread trapped, e.g. by panicking with `panic=abort`. Propagate to main `onerror`, which leaves the
+        // worker to be terminated by the main thread:
+        setTimeout(() => {
+            throw err;
+        });
+        return false;
+    }
+}
+
 // Wait for the main thread to send us the shared module/memory and work context.
 // Once we've got it, initialize it all with the `wasm_bindgen` global we imported via
 // `importScripts`.
+let initialized = false;
 self.onmessage = event => {
-    let [ module, memory, work ] = event.data;
+    // Instantiating twice would corrupt the thread state, so a duplicate initialization message is ignored
+    if (initialized) {
+        console.warn("wasm_thread: ignoring duplicate initialization message");
+        return;
+    }
+    initialized = true;
+
+    let { module, memory, work, release_module, pooled } = event.data;
+    prepare(event.data, pooled);
 
     init(module, memory).catch(err => {
         console.log(err);
@@ -17,11 +75,27 @@ self.onmessage = event => {
         // Rethrow to keep promise rejected and prevent execution of further commands:
         throw err;
     }).then(() => {
-        // Enter rust code by calling entry point defined in `lib.rs`.
-        // This executes closure defined by work context.
-        wasm_thread_entry_point(work);
+        // Drop the references to the compiled module set with `Builder::release_module_after_init`, keeping only the
+        // instance, so that it can be garbage collected
+        if (release_module) {
+            module = undefined;
+            init.__wbindgen_wasm_module = undefined;
+        }
+
+        if (!run(wasm_thread_entry_point, work)) {
+            return;
+        }
+
+        // A pooled worker stays alive once done, and runs the next job sent by the main thread on the same instance
+        if (pooled) {
+            self.onmessage = event => {
+                prepare(event.data, true);
+                run(wasm_thread_entry_point, event.data.work);
+            };
+            return;
+        }
 
         // Once done, terminate web worker
         close();
     });
-};
\ No newline at end of file
+};
@@ -0,0 +1,89 @@
+use std::{
+    fmt,
+    future::{Future, IntoFuture},
+    mem,
+    pin::Pin,
+};
+
+use super::{Builder, JoinHandle, Result};
+
+/// A thread that is only spawned once it is started or awaited, see [Builder::spawn_lazy].
+pub struct LazyHandle<T> {
+    state: LazyState<T>,
+}
+
+enum LazyState<T> {
+    Pending(Builder, Box<dyn FnOnce() -> T + Send>),
+    Started(JoinHandle<T>),
+    // Spawning failed, which consumed the closure
+    Failed,
+}
+
+impl<T: Send + 'static> LazyHandle<T> {
+    pub(crate) fn new<F>(builder: Builder, f: F) -> Self
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+    {
+        Self {
+            state: LazyState::Pending(builder, Box::new(f)),
+        }
+    }
+
+    /// Spawns the thread if it has not been spawned yet, and returns its [`JoinHandle`].
+    ///
+    /// Fails if the thread cannot be spawned, now or in an earlier call.
+    pub fn start(&mut self) -> std::io::Result<&JoinHandle<T>> {
+        if let LazyState::Pending(..) = self.state {
+            if let LazyState::Pending(builder, f) = mem::replace(&mut self.state, LazyState::Failed) {
+                self.state = LazyState::Started(builder.spawn(f)?);
+            }
+        }
+
+        match &self.state {
+            LazyState::Started(handle) => Ok(handle),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "the thread could not be spawned",
+            )),
+        }
+    }
+
+    /// Returns `true` if the thread has been spawned.
+    pub fn is_started(&self) -> bool {
+        matches!(self.state, LazyState::Started(_))
+    }
+
+    /// Spawns the thread if it has not been spawned yet, and waits for it to finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread could not be spawned, like [spawn](super::spawn).
+    pub async fn join_async(mut self) -> Result<T> {
+        self.start().expect("failed to spawn thread");
+        match self.state {
+            LazyState::Started(handle) => handle.join_async().await,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<T: Send + 'static> IntoFuture for LazyHandle<T> {
+    type Output = Result<T>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+    /// Spawns the thread if it has not been spawned yet, see [Self::join_async].
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.join_async())
+    }
+}
+
+impl<T> fmt::Debug for LazyHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.state {
+            LazyState::Pending(builder, _) => f.debug_tuple("LazyHandle::Pending").field(builder).finish(),
+            LazyState::Started(handle) => f.debug_tuple("LazyHandle::Started").field(handle).finish(),
+            LazyState::Failed => f.write_str("LazyHandle::Failed"),
+        }
+    }
+}
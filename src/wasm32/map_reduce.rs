@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use super::{spawn, try_join_all, Result};
+
+/// Maps `items` across `workers` threads, and reduces the results to a single value.
+///
+/// The items are split into contiguous shards of nearly equal size, one per thread. Each thread maps the items of its
+/// shard with `map_fn`, and reduces the mapped values starting from `identity` with `reduce_fn`. The partial results
+/// are then reduced in shard order on the calling thread, again starting from `identity`.
+///
+/// `reduce_fn` must be associative, and `identity` must be its identity element, e.g. `0` for addition, so that the
+/// result does not depend on how the items are sharded. As the shards are reduced in order, `reduce_fn` need not be
+/// commutative. At most one thread per item is spawned, and at least one.
+///
+/// If a thread fails, e.g. because `map_fn` panicked, the remaining threads are aborted as with [try_join_all], and
+/// the error of the failed thread is returned.
+pub async fn map_reduce<I, R, M, F>(items: Vec<I>, workers: usize, map_fn: M, reduce_fn: F, identity: R) -> Result<R>
+where
+    I: Send + 'static,
+    R: Clone + Send + 'static,
+    M: Fn(I) -> R + Send + Sync + 'static,
+    F: Fn(R, R) -> R + Send + Sync + 'static,
+{
+    let map_fn = Arc::new(map_fn);
+    let reduce_fn = Arc::new(reduce_fn);
+
+    // The first `len % workers` shards take one more item than the others
+    let workers = workers.clamp(1, items.len().max(1));
+    let (base, extra) = (items.len() / workers, items.len() % workers);
+    let mut items = items.into_iter();
+    let handles = (0..workers)
+        .map(|shard| {
+            let shard: Vec<I> = items.by_ref().take(base + (shard < extra) as usize).collect();
+            let (map_fn, reduce_fn, identity) = (map_fn.clone(), reduce_fn.clone(), identity.clone());
+            spawn(move || {
+                shard
+                    .into_iter()
+                    .map(|item| map_fn(item))
+                    .fold(identity, |acc, value| reduce_fn(acc, value))
+            })
+        })
+        .collect();
+
+    match try_join_all(handles).await {
+        Ok(partials) => Ok(partials
+            .into_iter()
+            .fold(identity, |acc, partial| reduce_fn(acc, partial))),
+        Err((_, result)) => result,
+    }
+}
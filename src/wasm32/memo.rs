@@ -0,0 +1,117 @@
+use std::{cell::RefCell, collections::HashMap, hash::Hash};
+
+use super::{Builder, Result};
+
+/// Runs a pure function in new threads, caching results by input so that repeated inputs do not spawn again.
+///
+/// The cache is local to the `MemoPool` and holds at most `capacity` results. Once it is full, the least recently used
+/// result is evicted to make room for a new one. Results of threads that panicked or were terminated are not cached.
+/// Inputs that are computed concurrently are not deduplicated, each of them spawns a thread until one has completed.
+pub struct MemoPool<I, T, F> {
+    builder: Builder,
+    f: F,
+    capacity: usize,
+    cache: RefCell<Cache<I, T>>,
+}
+
+struct Cache<I, T> {
+    // Results with the tick at which they were last used
+    entries: HashMap<I, (T, u64)>,
+    tick: u64,
+}
+
+impl<I, T, F> MemoPool<I, T, F>
+where
+    I: Hash + Eq + Clone + Send + 'static,
+    T: Clone + Send + 'static,
+    F: Fn(I) -> T + Clone + Send + 'static,
+{
+    /// Creates a pool that computes results with `f` in threads spawned by [Builder::new], caching up to `capacity`
+    /// results.
+    pub fn new(f: F, capacity: usize) -> Self {
+        Self::with_builder(Builder::new(), f, capacity)
+    }
+
+    /// Creates a pool that computes results with `f` in threads spawned by `builder`, caching up to `capacity` results.
+    pub fn with_builder(builder: Builder, f: F, capacity: usize) -> Self {
+        Self {
+            builder,
+            f,
+            capacity,
+            cache: RefCell::new(Cache {
+                entries: HashMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    /// Returns the cached result for `input`, or computes it in a new thread and caches it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    pub async fn compute(&self, input: I) -> Result<T> {
+        if let Some(result) = self.cache.borrow_mut().get(&input) {
+            return Ok(result);
+        }
+
+        let f = self.f.clone();
+        let their_input = input.clone();
+        let result = self
+            .builder
+            .clone()
+            .spawn(move || f(their_input))
+            .expect("failed to spawn thread")
+            .join_async()
+            .await?;
+
+        self.cache.borrow_mut().insert(input, result.clone(), self.capacity);
+        Ok(result)
+    }
+
+    /// Returns the number of cached results.
+    pub fn len(&self) -> usize {
+        self.cache.borrow().entries.len()
+    }
+
+    /// Returns `true` if no results are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached results.
+    pub fn clear(&self) {
+        self.cache.borrow_mut().entries.clear();
+    }
+}
+
+impl<I: Hash + Eq + Clone, T: Clone> Cache<I, T> {
+    fn get(&mut self, input: &I) -> Option<T> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(input).map(|(result, used)| {
+            *used = tick;
+            result.clone()
+        })
+    }
+
+    fn insert(&mut self, input: I, result: T, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&input) && self.entries.len() >= capacity {
+            let least_recent = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(input, _)| input.clone());
+            if let Some(least_recent) = least_recent {
+                self.entries.remove(&least_recent);
+            }
+        }
+
+        self.tick += 1;
+        self.entries.insert(input, (result, self.tick));
+    }
+}
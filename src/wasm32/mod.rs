@@ -1,47 +1,211 @@
-pub use std::thread::{current, sleep, Result, Thread, ThreadId};
+pub use std::thread::{current, sleep, Result};
 use std::{
-    cell::UnsafeCell,
+    any::Any,
+    arch::wasm32,
+    cell::{RefCell, UnsafeCell},
+    collections::BTreeMap,
     fmt,
+    future::{Future, IntoFuture},
     marker::PhantomData,
     mem,
     panic::{catch_unwind, AssertUnwindSafe},
+    pin::{pin, Pin},
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
-use scoped::ScopeData;
-pub use scoped::{scope, Scope, ScopedJoinHandle};
+pub use arena::{with_current_arena, Arena};
+pub use batch::{join_batch, BatchReport};
+pub use cancel::{restart, shutdown, CancellationToken};
+pub use checkpoint::Checkpoint;
+pub use config::{WorkerConfig, WorkerConfigView, WorkerCredentials, WorkerKind};
+#[cfg(feature = "deadlock_detection")]
+pub use deadlock::DeadlockDetected;
+pub use event_bus::{EventBus, EventSubscription};
+use futures::{
+    future::{self, Either},
+    stream::{FuturesUnordered, StreamExt},
+};
+pub use identity::{Thread, ThreadId};
+pub use interval::{IntervalStream, StopHandle};
+pub use join_error::JoinError;
+pub use lazy::LazyHandle;
+pub use map_reduce::map_reduce;
+pub use memo::MemoPool;
+pub use mutex::{AsyncMutex, AsyncMutexGuard, AsyncMutexLock};
+pub use once::SharedOnce;
+pub use oversubscription::OversubscriptionPolicy;
+pub use panics::{PanicReport, PanicStream};
+pub use pipeline::{Pipeline, StageInput};
+use pool::PoolKey;
+pub use pool::{clear_worker_pool, drain_worker_pool, idle_events, idle_worker_count, IdleEvents};
+pub use progress::{ProgressReporter, ProgressStream};
+pub use rate_limit::set_spawn_rate_limit;
+pub use registry::{find_workers_by_tag, join_by_token, WorkerId, WorkerInfo, WorkerToken};
+pub use resumable::Suspended;
+pub use retry::RetryableHandle;
+pub use ring::{FloatRingSink, FloatRingStream};
+pub use scoped::{scope, scope_async, Scope, ScopedJoinHandle};
+use scoped::{ScopeData, ScopeMember};
+pub use semaphore::{AsyncSemaphore, AsyncSemaphoreAcquire, AsyncSemaphorePermit};
+#[cfg(feature = "serde")]
+pub use serialized::SerdeJoinHandle;
+pub use service::{ServiceHandle, ServiceReply};
 use signal::Signal;
+pub use stats::{stats, ThreadStats};
+pub use timings::ThreadTimings;
+use timings::Timestamps;
 use utils::SpinLockMutex;
-pub use utils::{available_parallelism, get_wasm_bindgen_shim_script_path, get_worker_script, is_web_worker_thread};
+pub use utils::{
+    atomics_enabled, available_parallelism, available_parallelism_detailed, current_memory_pages,
+    get_wasm_bindgen_shim_script_path, get_worker_script, is_web_worker_thread, register_shim_version,
+    set_max_spawn_depth, set_memory_soft_limit, ParallelismInfo,
+};
+pub use waker::CrossThreadWaker;
 use wasm_bindgen::prelude::*;
-use web_sys::{DedicatedWorkerGlobalScope, Worker, WorkerOptions, WorkerType};
+use web_sys::{DedicatedWorkerGlobalScope, RequestCredentials, Worker, WorkerOptions, WorkerType};
 
+mod arena;
+mod batch;
+mod cancel;
+mod checkpoint;
+mod config;
+#[cfg(feature = "deadlock_detection")]
+mod deadlock;
+mod event_bus;
+mod identity;
+mod integrity;
+mod interval;
+mod join_error;
+mod lazy;
+mod map_reduce;
+mod memo;
+mod mutex;
+mod once;
+mod oom;
+mod oversubscription;
+mod panics;
+mod pipeline;
+mod pool;
+mod progress;
+mod rate_limit;
+mod registry;
+mod resumable;
+mod retry;
+mod ring;
 mod scoped;
+mod semaphore;
+#[cfg(feature = "serde")]
+mod serialized;
+mod service;
 mod signal;
+#[cfg(feature = "spawn_burst_detection")]
+mod spawn_burst;
+mod stats;
+#[cfg(feature = "stepped_executor")]
+mod stepped;
+mod timings;
 mod utils;
+mod waker;
 
 struct WebWorkerContext {
     func: Box<dyn FnOnce() + Send>,
+    state: Arc<ThreadState>,
+    config: WorkerConfigView,
+    // Size of the memory in pages when the thread was spawned, which the memory of the worker must have at least
+    memory_pages: usize,
+}
+
+// Fails if the worker was not instantiated with the memory of the spawning thread, e.g. because a custom worker script
+// passed a different memory. Shared memory only grows, so a memory smaller than when the thread was spawned, or than
+// needed to hold the context, is a different one, and reading the context from it would run garbage.
+unsafe fn check_memory(ptr: u32) -> std::result::Result<(), String> {
+    let available = wasm32::memory_size::<0>() * 65536;
+    let end = (ptr as usize).checked_add(mem::size_of::<WebWorkerContext>());
+    if end.map_or(true, |end| end > available) {
+        return Err(format!(
+            "thread context at {:#x} lies outside of the {} bytes of memory the web worker was instantiated with",
+            ptr, available
+        ));
+    }
+
+    let expected = (*(ptr as *const WebWorkerContext)).memory_pages;
+    if expected * 65536 > available {
+        return Err(format!(
+            "memory of the web worker has {} bytes, but the thread was spawned with {} bytes",
+            available,
+            expected * 65536
+        ));
+    }
+    Ok(())
+}
+
+thread_local! {
+    // State of the thread running in this web worker, if it was spawned by this crate
+    static CURRENT_STATE: RefCell<Option<Arc<ThreadState>>> = const { RefCell::new(None) };
+    // Configuration of the thread running in this web worker, if it was spawned by this crate
+    static CURRENT_CONFIG: RefCell<Option<WorkerConfigView>> = const { RefCell::new(None) };
 }
 
 /// Entry point for web workers
 #[wasm_bindgen]
 pub fn wasm_thread_entry_point(ptr: u32) {
-    let ctx = unsafe { Box::from_raw(ptr as *mut WebWorkerContext) };
-    (ctx.func)();
+    // Refuse to run on a memory other than the one the thread was spawned on. The error cannot be posted to the main
+    // thread as a message, which would be read from the wrong memory, so it is thrown to fail the worker instead.
+    if let Err(err) = unsafe { check_memory(ptr) } {
+        wasm_bindgen::throw_str(&format!("wasm_thread: refusing to run thread: {}", err));
+    }
+
+    // A worker runs a single thread. Entering again, e.g. because the initialization message was delivered twice, would
+    // run the context a second time, so the request is ignored. The context is leaked, as it may be the one in use.
+    if CURRENT_STATE.with(|state| state.borrow().is_some()) {
+        utils::console_warn("wasm_thread: ignoring repeated entry into an already running web worker");
+        return;
+    }
+
+    // The module has been instantiated, as this function is exported by it
+    WorkerMessage::Ready.post();
+
+    let WebWorkerContext {
+        func, state, config, ..
+    } = *unsafe { Box::from_raw(ptr as *mut WebWorkerContext) };
+    let pooled = config.pool_size.is_some();
+    arena::prepare(config.arena_size);
+    CURRENT_STATE.with(|current| *current.borrow_mut() = Some(state));
+    CURRENT_CONFIG.with(|current| *current.borrow_mut() = Some(config));
+    WorkerMessage::Started.post();
+    func();
+    arena::finish(pooled);
+    // A pooled worker runs the next thread on the same instance
+    CURRENT_STATE.with(|current| current.borrow_mut().take());
+    CURRENT_CONFIG.with(|current| current.borrow_mut().take());
     WorkerMessage::ThreadComplete.post();
 }
 
-/// Used to relay spawn requests from web workers to main thread
+/// Used to relay spawn requests from web workers to main thread, and to queue them while spawning is rate limited
 struct BuilderRequest {
     builder: Builder,
     context: WebWorkerContext,
+    // JS values cannot be relayed between threads, so this is only set for requests made on the main thread
+    transfer: Transfer,
+}
+
+/// JS values transferred into a web worker with its initialization message
+#[derive(Default)]
+pub(crate) struct Transfer {
+    stream: Option<web_sys::ReadableStream>,
+    ports: Vec<web_sys::MessagePort>,
 }
 
 impl BuilderRequest {
     pub unsafe fn spawn(self) {
-        self.builder.spawn_for_context(self.context);
+        if let Some(request) = oversubscription::admit(self) {
+            request.builder.spawn_for_context(request.context, request.transfer);
+        }
     }
 }
 
@@ -49,8 +213,20 @@ impl BuilderRequest {
 enum WorkerMessage {
     /// Request to spawn thread
     SpawnThread(BuilderRequest),
+    /// Worker has instantiated the wasm module
+    Ready,
+    /// Thread has started execution
+    Started,
     /// Thread has completed execution
     ThreadComplete,
+    /// Request to wake a main thread task
+    Wake(std::task::Waker),
+    /// Request to terminate all workers
+    TerminateAll,
+    /// Request to terminate the workers of threads spawned with a cancellation token
+    TerminateCancellable,
+    /// Request to terminate the worker of an aborted thread
+    Abort(Arc<ThreadState>),
 }
 
 impl WorkerMessage {
@@ -66,9 +242,124 @@ impl WorkerMessage {
     }
 }
 
+/// Main thread state that lives as long as a web worker
+struct WorkerResources {
+    id: WorkerId,
+    state: Arc<ThreadState>,
+    worker: Rc<Worker>,
+    // Pending timers and their handlers
+    timers: Vec<(i32, Closure<dyn FnMut()>)>,
+    // Cleanups to run on the main thread if the worker is terminated
+    critical_cleanups: Vec<CriticalCleanup>,
+    // Handlers attached to the worker, which must stay alive until they are detached
+    _on_message: Closure<dyn FnMut(&web_sys::MessageEvent)>,
+    _on_error: Closure<dyn FnMut(&web_sys::Event)>,
+}
+
+impl WorkerResources {
+    /// Releases the resources in `slot`, terminating the worker if requested. Does nothing if already released.
+    ///
+    /// Tears down in a fixed order. First the timers are disarmed and the worker is terminated along with its critical
+    /// cleanups, then the worker handlers are detached and the worker reference is dropped so it can be cleaned up by
+    /// GC, and finally the handlers are released. wasm-bindgen defers freeing a closure dropped during its own
+    /// invocation until it returns, so this can be called from any of the handlers.
+    fn release(slot: &RefCell<Option<Self>>, terminate: bool) {
+        let resources = slot.borrow_mut().take();
+        if let Some(resources) = resources {
+            for (timer_id, _) in &resources.timers {
+                utils::clear_timeout(*timer_id);
+            }
+            if terminate {
+                resources.worker.terminate();
+                // The thread can no longer run its destructors, so release what it may have held on its behalf
+                for cleanup in &resources.critical_cleanups {
+                    (cleanup.0)();
+                }
+            }
+            resources.worker.set_onmessage(None);
+            resources.worker.set_onerror(None);
+            registry::unregister(resources.id);
+            LIVE_RESOURCES.with(|live| live.borrow_mut().remove(&resources.id));
+            // A completed thread is settled once its worker has been returned to the pool
+            if terminate {
+                pool::settle(&resources.state);
+            }
+            drop(resources);
+            // A web worker slot has been freed up
+            unsafe { oversubscription::release() };
+        }
+    }
+}
+
+/// Callback for errors of web workers, see [set_global_worker_error_handler]
+type ErrorHandler = Rc<dyn Fn(WorkerInfo, JsValue)>;
+
+thread_local! {
+    // Handler set with `set_global_worker_error_handler`. Only used on the main thread.
+    static GLOBAL_ERROR_HANDLER: RefCell<Option<ErrorHandler>> = const { RefCell::new(None) };
+}
+
+/// Sets a handler that is called whenever the web worker of any thread spawned by this crate reports an error.
+///
+/// The handler receives information about the worker and the error event. It is called in addition to the join handle
+/// of the thread resolving with a [Termination::Trapped] error, which makes it a single place to log worker errors.
+/// Setting a handler replaces the previous one, and [clear_global_worker_error_handler] removes it.
+///
+/// # Panics
+///
+/// Panics in a web worker, because worker errors are received by the main thread.
+pub fn set_global_worker_error_handler(handler: impl Fn(WorkerInfo, JsValue) + 'static) {
+    if is_web_worker_thread() {
+        panic!("the global worker error handler can only be set on the main thread");
+    }
+
+    GLOBAL_ERROR_HANDLER.with(|slot| *slot.borrow_mut() = Some(Rc::new(handler)));
+}
+
+/// Removes the handler set with [set_global_worker_error_handler], if any.
+///
+/// This does nothing in a web worker, where no handler can be set.
+pub fn clear_global_worker_error_handler() {
+    GLOBAL_ERROR_HANDLER.with(|slot| slot.borrow_mut().take());
+}
+
+thread_local! {
+    // Closures registered with `register_global_closure`, until `shutdown` drops them
+    static GLOBAL_CLOSURES: RefCell<Vec<Box<dyn Any>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Keeps `closure` alive until [shutdown] is called on this thread, instead of leaking it with [Closure::forget].
+///
+/// This is meant for closures that JS holds on to for the lifetime of the page, e.g. global event handlers, so that
+/// they can be dropped deterministically on teardown, such as a route change in a single page application. Closures are
+/// bound to the thread that created them, so [shutdown] only drops the closures registered on the calling thread.
+/// Calling a closure from JS after it has been dropped throws an exception.
+pub fn register_global_closure<T: ?Sized + 'static>(closure: Closure<T>) {
+    GLOBAL_CLOSURES.with(|closures| closures.borrow_mut().push(Box::new(closure)));
+}
+
+// Drops the closures registered on this thread, see `register_global_closure`
+pub(crate) fn drop_global_closures() {
+    // Drop outside of the borrow, in case dropping a closure registers another one
+    let closures = GLOBAL_CLOSURES.with(|closures| mem::take(&mut *closures.borrow_mut()));
+    drop(closures);
+}
+
+thread_local! {
+    // Resources of all live workers, indexed for `terminate_all_workers`. Only used on the main thread.
+    static LIVE_RESOURCES: RefCell<BTreeMap<WorkerId, Rc<RefCell<Option<WorkerResources>>>>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
 static DEFAULT_BUILDER: Mutex<Option<Builder>> = Mutex::new(None);
 
 /// Thread factory, which can be used in order to configure the properties of a new thread.
+///
+/// The builder mirrors [std::thread::Builder]: [Self::name], [Self::stack_size] and [Self::spawn] take the same
+/// arguments, and [Self::spawn] runs a plain `FnOnce() -> T` closure and returns an [std::io::Result] of a
+/// [`JoinHandle<T>`](JoinHandle), without exposing the web worker. Code using the std builder is ported by changing the
+/// import path only. The main thread must not block, so join with [JoinHandle::join_async] there instead of
+/// [JoinHandle::join].
 #[derive(Debug, Clone)]
 pub struct Builder {
     // A name for the thread-to-be, for identification in panic messages
@@ -81,8 +372,73 @@ pub struct Builder {
     stack_size: Option<usize>,
     // Url of the `wasm_bindgen` generated shim `.js` script to use as web worker entry point
     wasm_bindgen_shim_url: Option<String>,
+    // Version key of a shim URL registered with `register_shim_version`
+    shim_version: Option<String>,
+    // Maximum wall-clock lifetime after which the worker is forcibly terminated
+    max_lifetime: Option<Duration>,
+    // Time within which the thread must start after its worker was created
+    startup_timeout: Option<Duration>,
+    // Lifetime after which the thread is asked to stop, and the grace period after which it is terminated
+    soft_deadline: Option<(Duration, Duration)>,
+    // Wasm module bytes to instantiate the worker from instead of the module of the spawning instance
+    module_bytes: Option<ModuleBytes>,
+    // Whether the worker drops its reference to the module once instantiated
+    release_module_after_init: bool,
+    // Maximum number of idle workers kept for reuse, if the worker is pooled
+    pool_size: Option<usize>,
+    // Key of the threads that preferably run on the same pooled worker
+    affinity: Option<u64>,
+    // Size of the private bump allocation region of the thread
+    arena_size: Option<usize>,
+    // Subresource Integrity metadata the worker script must match
+    script_integrity: Option<String>,
+    // An application-defined key for looking up the worker in the registry
+    tag: Option<u64>,
+    // URLs of classic scripts to import before the `wasm_bindgen` shim
+    import_scripts: Vec<String>,
+    // Token the thread can observe to be cancelled cooperatively
+    cancellation_token: Option<CancellationToken>,
+    // Whether the thread gets a child of the spawning thread's token if no token is set
+    inherit_cancel: bool,
+    // Type-erased slot of a `Checkpoint`
+    checkpoint: Option<Arc<dyn Any + Send + Sync>>,
+    // JSON encoded extra value for the initialization message
+    init_extra: Option<String>,
+    // Cleanups to run on the main thread if the worker is terminated
+    critical_cleanups: Vec<CriticalCleanup>,
+    // Position of the thread within its batch
+    worker_index: Option<usize>,
+    // What to do when there are more live workers than cores
+    oversubscription_policy: OversubscriptionPolicy,
+    // Type of a custom worker script
+    worker_type: Option<WorkerKind>,
+    // Credentials for fetching the worker script
+    credentials: Option<WorkerCredentials>,
+}
+
+// Raw wasm module bytes, shared between builder clones
+#[derive(Clone)]
+struct ModuleBytes(Arc<[u8]>);
+
+impl fmt::Debug for ModuleBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0.len())
+    }
+}
+
+// Cleanup registered with `Builder::critical_cleanup`, shared between builder clones
+#[derive(Clone)]
+struct CriticalCleanup(Arc<dyn Fn() + Send + Sync>);
+
+impl fmt::Debug for CriticalCleanup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<cleanup>")
+    }
 }
 
+// Callback registered with `JoinHandle::on_discarded`
+type DiscardedResultCallback<'a, T> = Box<dyn FnOnce(Result<T>) + Send + 'a>;
+
 impl Default for Builder {
     fn default() -> Self {
         DEFAULT_BUILDER.lock_spin().unwrap().clone().unwrap_or(Self::empty())
@@ -103,6 +459,27 @@ impl Builder {
             worker_script_url: None,
             stack_size: None,
             wasm_bindgen_shim_url: None,
+            shim_version: None,
+            max_lifetime: None,
+            startup_timeout: None,
+            soft_deadline: None,
+            module_bytes: None,
+            release_module_after_init: false,
+            pool_size: None,
+            affinity: None,
+            arena_size: None,
+            script_integrity: None,
+            tag: None,
+            import_scripts: Vec::new(),
+            cancellation_token: None,
+            inherit_cancel: false,
+            checkpoint: None,
+            init_extra: None,
+            critical_cleanups: Vec::new(),
+            worker_index: None,
+            oversubscription_policy: OversubscriptionPolicy::Allow,
+            worker_type: None,
+            credentials: None,
         }
     }
 
@@ -111,6 +488,27 @@ impl Builder {
         *DEFAULT_BUILDER.lock_spin().unwrap() = Some(self);
     }
 
+    /// Sets `builder` as global default for the duration of `f`, and restores the previous default afterwards.
+    ///
+    /// The previous default is restored even if `f` panics, so temporary overrides do not leak. The default is global,
+    /// so builders created concurrently by other threads also see the override while `f` runs.
+    pub fn with_default_scoped<F, R>(builder: Builder, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        // Restores the previous default on drop, including while unwinding
+        struct Restore(Option<Builder>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                *DEFAULT_BUILDER.lock_spin().unwrap() = self.0.take();
+            }
+        }
+
+        let _restore = Restore(DEFAULT_BUILDER.lock_spin().unwrap().replace(builder));
+        f()
+    }
+
     /// Sets the prefix of the thread.
     pub fn prefix(mut self, prefix: String) -> Builder {
         self.prefix = Some(prefix);
@@ -122,6 +520,40 @@ impl Builder {
         self
     }
 
+    /// Sets the type of the script set with [Self::worker_script_url].
+    ///
+    /// The bundled worker script is a module if the `es_modules` feature is enabled and a classic script otherwise,
+    /// so this setting only applies to custom scripts, which default to the same type.
+    pub fn worker_type(mut self, worker_type: WorkerKind) -> Builder {
+        self.worker_type = Some(worker_type);
+        self
+    }
+
+    /// Sets the credentials sent when the worker script is fetched. Defaults to the browser default.
+    pub fn credentials(mut self, credentials: WorkerCredentials) -> Builder {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Requires the worker script to match the [Subresource Integrity] metadata `integrity`, e.g.
+    /// `sha384-<base64 digest>`.
+    ///
+    /// Browsers do not check the integrity of worker scripts, so the script is fetched and verified by the main thread,
+    /// and the worker is created from a blob URL of the verified content. This applies to the script set with
+    /// [Self::worker_script_url], and otherwise to the `wasm_bindgen` shim imported by the bundled worker script, which
+    /// must then not import other scripts by relative URLs. Each script is fetched only once per page. Verification
+    /// requires a secure context.
+    ///
+    /// Verification finishes after spawning returns, so a mismatch is not reported by the spawn. Instead, the thread
+    /// never runs, a warning is logged, and joining it yields an error with a [Termination::IntegrityCheckFailed]
+    /// payload.
+    ///
+    /// [Subresource Integrity]: https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity
+    pub fn script_integrity(mut self, integrity: String) -> Builder {
+        self.script_integrity = Some(integrity);
+        self
+    }
+
     /// Sets the name of the thread.
     ///
     /// If not set, the default name is autogenerated.
@@ -141,96 +573,665 @@ impl Builder {
     }
 
     /// Sets the URL of wasm_bindgen generated shim script.
+    ///
+    /// The URL must be absolute, otherwise spawning fails. If not set, the URL is detected automatically, and spawning
+    /// fails if that is not possible.
     pub fn wasm_bindgen_shim_url(mut self, url: String) -> Builder {
         self.wasm_bindgen_shim_url = Some(url);
         self
     }
 
+    /// Selects the `wasm_bindgen` shim script registered for `version` with [register_shim_version].
+    ///
+    /// This takes precedence over [Self::wasm_bindgen_shim_url]. Spawning fails if no shim is registered for `version`.
+    pub fn shim_version(mut self, version: String) -> Builder {
+        self.shim_version = Some(version);
+        self
+    }
+
+    /// Sets the maximum wall-clock lifetime of the thread.
+    ///
+    /// If the thread has not completed once `lifetime` has elapsed since it was spawned, its web worker is terminated
+    /// and joining it yields an error with a [Termination::LifetimeExceeded] payload.
+    ///
+    /// # Warning
+    ///
+    /// Termination is abrupt: no destructors are run and any locks held by the thread in shared memory (including the
+    /// allocator lock) are never released. The worker also stops asynchronously, so it may still run briefly after the
+    /// join handle resolves. This option is therefore ignored for threads that may borrow data, i.e. scoped threads and
+    /// threads spawned with [Self::spawn_unchecked], because the data could otherwise be dropped while the thread is
+    /// still accessing it.
+    pub fn max_lifetime(mut self, lifetime: Duration) -> Builder {
+        self.max_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Sets the time within which the thread must start running once its web worker has been created.
+    ///
+    /// If the worker has not fetched its script, instantiated the wasm module and started the thread by then, e.g.
+    /// because the script is unreachable over a slow network, the worker is terminated and joining the thread yields an
+    /// error with a [Termination::StartupTimedOut] payload. This tells deployment issues apart from slow work, which
+    /// is limited by [Self::max_lifetime] instead. Time the thread spends queued before its worker is created, e.g. by
+    /// [set_spawn_rate_limit], does not count.
+    ///
+    /// As with [Self::max_lifetime], this option is ignored for threads that may borrow data.
+    pub fn startup_timeout(mut self, timeout: Duration) -> Builder {
+        self.startup_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a deadline after which the thread is asked to stop, and a grace period after which it is terminated.
+    ///
+    /// Once `deadline` has elapsed since the thread was spawned, its [cancellation token](cancellation_token) is
+    /// cancelled. If the thread is still running after another `grace`, it is terminated as with [Self::max_lifetime],
+    /// and joining it yields an error with a [Termination::LifetimeExceeded] payload. A thread that returns within the
+    /// grace period is joined normally.
+    ///
+    /// The thread always receives a token of its own, which is a [child](CancellationToken::child_token) of the token
+    /// set with [Self::cancellation_token] or inherited with [Self::inherit_cancel], if any. For threads that are never
+    /// terminated (see [Self::max_lifetime]), only the stop request is sent.
+    pub fn soft_deadline(mut self, deadline: Duration, grace: Duration) -> Builder {
+        self.soft_deadline = Some((deadline, grace));
+        self
+    }
+
+    /// Tags the thread with an application-defined key.
+    ///
+    /// While the thread is running, its web worker can be found with [find_workers_by_tag].
+    pub fn tag(mut self, tag: u64) -> Builder {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Sets scripts to import into the web worker before the `wasm_bindgen` shim is loaded.
+    ///
+    /// The scripts are loaded with `importScripts`, so they can define globals for the thread to use. This requires
+    /// classic workers, i.e. the `es_modules` feature to be disabled. Spawning fails if any of the URLs is not
+    /// absolute, or if the `es_modules` feature is enabled.
+    pub fn import_scripts(mut self, urls: Vec<String>) -> Builder {
+        self.import_scripts = urls;
+        self
+    }
+
+    /// Sets the token used to cancel the thread.
+    ///
+    /// The thread can retrieve it with [cancellation_token]. Cancellation is cooperative, so the thread has to check
+    /// the token itself.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Builder {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Sets whether the thread inherits the cancellation token of the spawning thread.
+    ///
+    /// If enabled and no token has been set with [Self::cancellation_token], the thread receives a
+    /// [child](CancellationToken::child_token) of the spawning thread's token, so that cancelling the parent also
+    /// cancels the child. This has no effect if the spawning thread has no token.
+    pub fn inherit_cancel(mut self, inherit: bool) -> Builder {
+        self.inherit_cancel = inherit;
+        self
+    }
+
+    /// Attaches a checkpoint to the thread, for saving partial results that survive the thread failing.
+    ///
+    /// The thread can retrieve the checkpoint with [Checkpoint::current], and the spawning thread can read it through
+    /// [JoinHandle::last_checkpoint] or its own clone of `checkpoint`.
+    pub fn with_checkpoint<C: Send + 'static>(mut self, checkpoint: &Checkpoint<C>) -> Builder {
+        self.checkpoint = Some(checkpoint.erase());
+        self
+    }
+
+    /// Sets the index of the thread within a batch of threads, which it can read with [current_worker_index].
+    ///
+    /// This is set by [Self::spawn_batch], and lets threads partition data by their index without passing it
+    /// explicitly.
+    pub fn worker_index(mut self, index: usize) -> Builder {
+        self.worker_index = Some(index);
+        self
+    }
+
+    /// Sets what happens when the web worker of the thread would exceed the [available_parallelism].
+    ///
+    /// The policy applies when the web worker is created, which may be later than spawning if spawning is
+    /// [rate limited](set_spawn_rate_limit). All live web workers created by this crate count, regardless of their
+    /// own policy. With [OversubscriptionPolicy::Cap], spawning still returns immediately, but the web worker is only
+    /// created once the number of live web workers has dropped below the available parallelism.
+    pub fn oversubscription_policy(mut self, policy: OversubscriptionPolicy) -> Builder {
+        self.oversubscription_policy = policy;
+        self
+    }
+
+    /// Registers a cleanup that the main thread runs if this crate terminates the web worker.
+    ///
+    /// A terminated thread runs no destructors, so resources it holds in shared memory, e.g. a lock, are never
+    /// released. The cleanup runs right after the worker has been terminated, for any [Termination] reason, and can
+    /// release such resources on the thread's behalf. It does not run if the thread completes normally. Several
+    /// cleanups run in the order they were registered.
+    ///
+    /// The cleanup must not block, as it runs on the main thread.
+    pub fn critical_cleanup<F>(mut self, cleanup: F) -> Builder
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.critical_cleanups.push(CriticalCleanup(Arc::new(cleanup)));
+        self
+    }
+
+    /// Sets an extra value that is delivered to the web worker with its initialization message.
+    ///
+    /// The initialization message is an object with `module`, `memory`, `work`, `name` and `extra` fields. The bundled
+    /// worker scripts store `extra` as `self.wasm_thread_init_extra` before running any Rust code, where custom glue
+    /// and the thread itself can read it, see [init_extra]. Scripts set with [Self::worker_script_url] receive the
+    /// same message.
+    ///
+    /// JS values cannot be shared between threads, so the value is copied as JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `extra` cannot be serialized as JSON, e.g. because it is `undefined`, a function or a cyclic object.
+    pub fn init_extra(mut self, extra: JsValue) -> Builder {
+        let json = js_sys::JSON::stringify(&extra)
+            .ok()
+            .and_then(|json| json.as_string())
+            .expect("init extra value cannot be serialized as JSON");
+        self.init_extra = Some(json);
+        self
+    }
+
+    /// Sets the wasm module bytes the web worker is instantiated from.
+    ///
+    /// By default workers are instantiated from the compiled module of the spawning instance. Providing the bytes
+    /// instead lets the worker compile them itself, which helps in environments where compiled modules cannot be
+    /// shared. The bytes must be the same module that is currently running, as the worker shares its memory.
+    pub fn with_module_bytes(mut self, bytes: &[u8]) -> Builder {
+        self.module_bytes = Some(ModuleBytes(bytes.into()));
+        self
+    }
+
+    /// Sets whether the web worker drops its reference to the compiled wasm module once it has been instantiated.
+    ///
+    /// The worker only needs the module to instantiate itself, so releasing it afterwards lets the module be garbage
+    /// collected in the worker, which saves memory for long-running workers on memory constrained devices. The worker
+    /// can then no longer instantiate the module again, and [wasm_bindgen::module] returns `undefined` in it. Threads
+    /// spawned from the worker are unaffected, as they are created by the main thread. Scripts set with
+    /// [Self::worker_script_url] receive a `release_module` field in the initialization message instead.
+    pub fn release_module_after_init(mut self, release: bool) -> Builder {
+        self.release_module_after_init = release;
+        self
+    }
+
+    /// Runs the thread on a pooled web worker, keeping up to `size` idle workers for reuse.
+    ///
+    /// Creating a web worker fetches its script and instantiates the wasm module, which dominates the cost of
+    /// short-lived threads. A pooled worker is kept once its thread completes, and runs the next pooled thread with the
+    /// same worker script, type and credentials on the same instance. Up to `size` idle workers are kept per script,
+    /// and further workers are terminated once their thread completes. Set the pool size on the default builder with
+    /// [Self::set_default] to pool the workers of [spawn].
+    ///
+    /// Whether pooled or not, every worker shares the compiled module and the memory of the spawning instance, so
+    /// pooling only saves creating the worker and instantiating the module, at the cost of keeping idle workers alive.
+    ///
+    /// Each thread still has its own join handle, which resolves once. Thread locals are not reset between threads, and
+    /// async tasks that a thread left running on its worker keep running. A worker whose thread trapped, e.g. because
+    /// it panicked with `panic=abort`, or was terminated is never reused. Idle workers are terminated by
+    /// [clear_worker_pool].
+    pub fn pool_size(mut self, size: usize) -> Builder {
+        self.pool_size = Some(size);
+        self
+    }
+
+    /// Prefers running the thread on the pooled web worker that last ran a thread with the same affinity `key`.
+    ///
+    /// Pooled workers keep their thread locals, so related threads can reuse state left warm by each other, e.g. caches
+    /// or decoded data. Affinity is a best-effort hint: if the preferred worker is busy, the thread runs on another
+    /// idle worker, preferring one without affinity, or on a new worker. Either way, the worker becomes the preferred
+    /// one for `key` once the thread completes. Idle workers are shared by all threads with the same settings, so a
+    /// thread without affinity may still take the preferred worker when no other worker is idle. This has no effect
+    /// unless the thread is [pooled](Self::pool_size).
+    pub fn affinity(mut self, key: u64) -> Builder {
+        self.affinity = Some(key);
+        self
+    }
+
+    /// Gives the thread an [Arena] of `size` bytes, a bump allocator for scratch allocations that do not contend with
+    /// other threads for the global allocator.
+    ///
+    /// The region is allocated once from the shared memory when the thread starts, and is private to the web worker.
+    /// The thread accesses it with [with_current_arena]. All allocations are freed when the thread completes. A
+    /// [pooled](Self::pool_size) worker keeps the region and resets it for the next thread with the same arena size.
+    pub fn with_arena(mut self, size: usize) -> Builder {
+        self.arena_size = Some(size);
+        self
+    }
+
     /// Spawns a new thread by taking ownership of the `Builder`, and returns an
     /// [std::io::Result] to its [`JoinHandle`].
+    ///
+    /// Values captured by `f` are dropped on the new thread once `f` returns. A panic while dropping them counts as a
+    /// panic of the thread, so joining yields an error.
     pub fn spawn<F, T>(self, f: F) -> std::io::Result<JoinHandle<T>>
     where
         F: FnOnce() -> T,
         F: Send + 'static,
         T: Send + 'static,
     {
-        unsafe { self.spawn_unchecked(f) }
+        // SAFETY: the closure and its result are `'static`, so they cannot outlive any borrowed data
+        Ok(JoinHandle(unsafe {
+            self.spawn_unchecked_(f, None, true, Transfer::default())
+        }?))
     }
 
-    /// Spawns a new thread without any lifetime restrictions by taking ownership
-    /// of the `Builder`, and returns an [std::io::Result] to its [`JoinHandle`].
+    /// Spawns a new thread like [Self::spawn], naming it after the `file:line` of the call site unless a name is set.
     ///
-    /// # Safety
+    /// This helps to tell which code spawned a web worker, e.g. one that is running away, in the devtools and in
+    /// [WorkerInfo::name]. A [prefix](Self::prefix) is still prepended to the name. See also
+    /// [spawn_here!](crate::spawn_here).
+    #[track_caller]
+    pub fn spawn_here<F, T>(mut self, f: F) -> std::io::Result<JoinHandle<T>>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        if self.name.is_none() {
+            let location = std::panic::Location::caller();
+            self.name = Some(format!("{}:{}", location.file(), location.line()));
+        }
+        self.spawn(f)
+    }
+
+    /// Spawns a new thread that handles messages sent through the returned [ServiceHandle].
     ///
-    /// The caller has to ensure that no references in the supplied thread closure
-    /// or its return type can outlive the spawned thread's lifetime. This can be
-    /// guaranteed in two ways:
+    /// Each message is passed to `handler`, and the output of the returned future, if any, is sent back as the reply.
+    /// Messages are handled one at a time in the order they were sent, until the service is
+    /// [stopped](ServiceHandle::stop) or the handle is dropped.
     ///
-    /// - ensure that [`join`][`JoinHandle::join`] is called before any referenced
-    /// data is dropped
-    /// - use only types with `'static` lifetime bounds, i.e., those with no or only
-    /// `'static` references (both [`Builder::spawn`]
-    /// and [`spawn`] enforce this property statically)
-    pub unsafe fn spawn_unchecked<'a, F, T>(self, f: F) -> std::io::Result<JoinHandle<T>>
+    /// # Warning
+    ///
+    /// The handler futures are polled by a blocking executor, which keeps the web worker busy while waiting for
+    /// messages. JS promises therefore cannot make progress inside the handler.
+    pub fn spawn_service<M, R, H, Fut>(self, handler: H) -> std::io::Result<ServiceHandle<M, R>>
+    where
+        M: Send + 'static,
+        R: Send + 'static,
+        H: FnMut(M) -> Fut,
+        H: Send + 'static,
+        Fut: Future<Output = Option<R>>,
+    {
+        ServiceHandle::spawn(self, handler)
+    }
+
+    /// Spawns a new thread that runs `f` every `interval`, and streams each result back through the returned
+    /// [IntervalStream] until it is stopped with the returned [StopHandle].
+    ///
+    /// The thread waits for `interval` after each run, so runs start at least `interval` apart. The futures returned
+    /// by `f` are polled by a blocking executor, as with [Self::spawn_service], so JS promises cannot make progress
+    /// inside them.
+    pub fn spawn_interval<F, Fut, T>(self, interval: Duration, f: F) -> std::io::Result<(IntervalStream<T>, StopHandle)>
+    where
+        F: FnMut() -> Fut,
+        F: Send + 'static,
+        Fut: Future<Output = T>,
+        T: Send + 'static,
+    {
+        interval::spawn(self, interval, f)
+    }
+
+    /// Creates a task that runs in a new thread each time it is [run](RetryableHandle::run) or
+    /// [retried](RetryableHandle::retry).
+    ///
+    /// `make_f` is called to build a fresh closure for every attempt, so the task can be run again after a previous
+    /// attempt consumed its closure by panicking or being terminated. No thread is spawned until the task is run.
+    pub fn spawn_retryable<M, F, T>(self, make_f: M) -> RetryableHandle<M, T>
     where
+        M: Fn() -> F,
         F: FnOnce() -> T,
-        F: Send + 'a,
-        T: Send + 'a,
+        F: Send + 'static,
+        T: Send + 'static,
     {
-        Ok(JoinHandle(unsafe { self.spawn_unchecked_(f, None) }?))
+        RetryableHandle::new(self, make_f)
     }
 
-    pub(crate) unsafe fn spawn_unchecked_<'a, 'scope, F, T>(
-        self,
-        f: F,
-        scope_data: Option<Arc<ScopeData>>,
-    ) -> std::io::Result<JoinInner<'scope, T>>
+    /// Creates a thread that is only spawned once it is [started](LazyHandle::start) or awaited.
+    ///
+    /// No web worker is created before that, so dropping a handle that was never started costs nothing. This suits
+    /// speculative work, where some of the threads turn out not to be needed.
+    pub fn spawn_lazy<F, T>(self, f: F) -> LazyHandle<T>
     where
         F: FnOnce() -> T,
-        F: Send + 'a,
-        T: Send + 'a,
-        'scope: 'a,
+        F: Send + 'static,
+        T: Send + 'static,
     {
-        let my_signal = Arc::new(Signal::new());
-        let their_signal = my_signal.clone();
+        LazyHandle::new(self, f)
+    }
 
-        let my_packet: Arc<Packet<'scope, T>> = Arc::new(Packet {
-            scope: scope_data,
-            result: UnsafeCell::new(None),
-            _marker: PhantomData,
-        });
-        let their_packet = my_packet.clone();
+    /// Spawns `count` threads running clones of `f`, each with its [index](Self::worker_index) in `0..count`, and
+    /// returns an [std::io::Result] to their [`JoinHandle`]s in index order.
+    ///
+    /// If spawning any of the threads fails, the error is returned and the threads spawned so far keep running
+    /// detached.
+    pub fn spawn_batch<F, T>(self, count: usize, f: F) -> std::io::Result<Vec<JoinHandle<T>>>
+    where
+        F: FnOnce() -> T + Clone,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        #[cfg(feature = "spawn_burst_detection")]
+        let _exempt = spawn_burst::exempt();
+        (0..count)
+            .map(|index| self.clone().worker_index(index).spawn(f.clone()))
+            .collect()
+    }
 
-        // Pass `f` in `MaybeUninit` because actually that closure might *run longer than the lifetime of `F`*.
-        // See <https://github.com/rust-lang/rust/issues/101983> for more details.
-        // To prevent leaks we use a wrapper that drops its contents.
-        #[repr(transparent)]
-        struct MaybeDangling<T>(mem::MaybeUninit<T>);
-        impl<T> MaybeDangling<T> {
-            fn new(x: T) -> Self {
-                MaybeDangling(mem::MaybeUninit::new(x))
-            }
-            fn into_inner(self) -> T {
-                // SAFETY: we are always initiailized.
-                let ret = unsafe { self.0.assume_init_read() };
-                // Make sure we don't drop.
-                mem::forget(self);
-                ret
-            }
+    /// Spawns a new thread that receives `stream`, and returns an [std::io::Result] to its [`JoinHandle`].
+    ///
+    /// The stream is transferred into the web worker along with its initialization message, so its chunks are read
+    /// by the worker without passing through the main thread. The stream is locked on the spawning side afterwards.
+    ///
+    /// `f` is called synchronously, so JS promises, including the ones returned by stream readers, cannot settle while
+    /// it runs. The web worker closes once `f` returns, unless it is [pooled](Self::pool_size), in which case tasks
+    /// that `f` spawned on the worker to read the stream keep running.
+    ///
+    /// Spawning fails if called from a web worker, because JS values cannot be relayed to the main thread, or if the
+    /// browser does not support transferring streams.
+    pub fn spawn_with_stream<F, T>(self, stream: web_sys::ReadableStream, f: F) -> std::io::Result<JoinHandle<T>>
+    where
+        F: FnOnce(web_sys::ReadableStream) -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        if is_web_worker_thread() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "streams can only be transferred into threads spawned from the main thread",
+            ));
         }
-        impl<T> Drop for MaybeDangling<T> {
-            fn drop(&mut self) {
-                // SAFETY: we are always initiailized.
-                unsafe { self.0.assume_init_drop() };
-            }
+        if !utils::can_transfer_streams() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "this browser does not support transferring streams",
+            ));
         }
 
-        let f = MaybeDangling::new(f);
-        let main = Box::new(move || {
+        let f = move || {
+            // The worker script stored the transferred stream in its global scope
+            let global = js_sys::global();
+            let stream = js_sys::Reflect::get(&global, &"wasm_thread_stream".into()).unwrap();
+            js_sys::Reflect::delete_property(&global, &"wasm_thread_stream".into()).unwrap();
+            f(stream.unchecked_into())
+        };
+        // SAFETY: the closure and its result are `'static`, so they cannot outlive any borrowed data
+        Ok(JoinHandle(unsafe {
+            let transfer = Transfer {
+                stream: Some(stream),
+                ..Transfer::default()
+            };
+            self.spawn_unchecked_(f, None, true, transfer)
+        }?))
+    }
+
+    /// Spawns a new thread that receives `ports`, and returns an [std::io::Result] to its [`JoinHandle`].
+    ///
+    /// The ports are transferred into the web worker along with its initialization message, so messages posted to
+    /// them, e.g. audio buffers from an `AudioWorklet`, reach the worker without passing through the main thread. The
+    /// ports are passed to `f` in the same order.
+    ///
+    /// `f` is called synchronously, so handlers set on the ports do not run while it does. The ports can still be used
+    /// to post messages, and the web worker closes once `f` returns.
+    ///
+    /// All ports are transferred at once, so either all of them or none are transferred. Spawning fails if called from
+    /// a web worker, because JS values cannot be relayed to the main thread, or if a port is passed more than once. If
+    /// a port cannot be transferred, e.g. because it has already been transferred elsewhere, the thread never runs and
+    /// joining it fails with [Termination::Trapped].
+    pub fn spawn_with_ports<F, T>(self, ports: Vec<web_sys::MessagePort>, f: F) -> std::io::Result<JoinHandle<T>>
+    where
+        F: FnOnce(Vec<web_sys::MessagePort>) -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        if is_web_worker_thread() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "ports can only be transferred into threads spawned from the main thread",
+            ));
+        }
+        for (i, port) in ports.iter().enumerate() {
+            if ports[..i].contains(port) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "a port can only be transferred once",
+                ));
+            }
+        }
+
+        let f = move || {
+            // The worker script stored the transferred ports in its global scope
+            let global = js_sys::global();
+            let ports = js_sys::Reflect::get(&global, &"wasm_thread_ports".into()).unwrap();
+            js_sys::Reflect::delete_property(&global, &"wasm_thread_ports".into()).unwrap();
+            let ports: js_sys::Array = ports.unchecked_into();
+            f(ports.iter().map(JsCast::unchecked_into).collect())
+        };
+        let transfer = Transfer {
+            ports,
+            ..Transfer::default()
+        };
+        // SAFETY: the closure and its result are `'static`, so they cannot outlive any borrowed data
+        Ok(JoinHandle(unsafe { self.spawn_unchecked_(f, None, true, transfer) }?))
+    }
+
+    /// Spawns a new thread that stores its result directly into `slot`, and returns an [std::io::Result] to a
+    /// [`JoinHandle`] that signals its completion.
+    ///
+    /// The result is never moved through the join handle, so observers holding a clone of `slot` can read it as soon
+    /// as the thread has completed. If the thread panics or is terminated, `slot` is left untouched and the error is
+    /// reported by the join handle instead.
+    pub fn spawn_into<F, T>(self, slot: Arc<Mutex<Option<T>>>, f: F) -> std::io::Result<JoinHandle<()>>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawn(move || {
+            let value = f();
+            *slot.lock_spin().unwrap() = Some(value);
+        })
+    }
+
+    /// Spawns a new thread that moves `data` into the future returned by `f` and runs it to completion, and returns an
+    /// [std::io::Result] to its [`JoinHandle`].
+    ///
+    /// Only `data` and `f` are sent to the new thread. The future is created and polled there, so it does not have to
+    /// be `Send`, and because it owns `data` it can freely borrow from it across `.await` points. The lifetime
+    /// erasure of [Builder::spawn_unchecked] does not apply to the future: it never leaves the thread that created
+    /// it, and everything that crosses threads is `'static`, so the thread can be terminated like any other.
+    ///
+    /// # Warning
+    ///
+    /// The future is polled by a blocking executor, which keeps the web worker busy until it completes. JS promises
+    /// therefore cannot make progress inside the future.
+    pub fn spawn_owning<D, F, Fut>(self, data: D, f: F) -> std::io::Result<JoinHandle<Fut::Output>>
+    where
+        D: Send + 'static,
+        F: FnOnce(D) -> Fut,
+        F: Send + 'static,
+        Fut: Future,
+        Fut::Output: Send + 'static,
+    {
+        self.spawn(move || futures::executor::block_on(f(data)))
+    }
+
+    /// Spawns a new thread without any lifetime restrictions by taking ownership
+    /// of the `Builder`, and returns an [std::io::Result] to its [`JoinHandle`].
+    ///
+    /// # Safety
+    ///
+    /// The caller has to ensure that no references in the supplied thread closure
+    /// or its return type can outlive the spawned thread's lifetime. This can be
+    /// guaranteed in two ways:
+    ///
+    /// - ensure that [`join`][`JoinHandle::join`] is called before any referenced
+    /// data is dropped
+    /// - use only types with `'static` lifetime bounds, i.e., those with no or only
+    /// `'static` references (both [`Builder::spawn`]
+    /// and [`spawn`] enforce this property statically)
+    ///
+    /// Because a terminated thread may keep running briefly after its join handle
+    /// resolves, threads spawned with this function are never terminated by the
+    /// crate: [`Builder::max_lifetime`] is ignored and [`terminate_all_workers`]
+    /// leaves them running. Joining therefore always waits for the closure to
+    /// return or trap.
+    ///
+    /// The closure is moved to the web worker through shared memory, so it must
+    /// not capture thread-local state of the spawning thread, such as `JsValue`s.
+    /// The `Send` bound rules this out for safe types.
+    pub unsafe fn spawn_unchecked<'a, F, T>(self, f: F) -> std::io::Result<JoinHandle<T>>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'a,
+        T: Send + 'a,
+    {
+        Ok(JoinHandle(unsafe {
+            self.spawn_unchecked_(f, None, false, Transfer::default())
+        }?))
+    }
+
+    // Spawns the thread. `terminable` must only be set if `F` and `T` are `'static`, because a terminated thread may
+    // keep running after its join handle has resolved. Values can only be transferred from the main thread.
+    pub(crate) unsafe fn spawn_unchecked_<'a, 'scope, F, T>(
+        mut self,
+        f: F,
+        scope_data: Option<Arc<ScopeData>>,
+        terminable: bool,
+        transfer: Transfer,
+    ) -> std::io::Result<JoinInner<'scope, T>>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'a,
+        T: Send + 'a,
+        'scope: 'a,
+    {
+        cancel::check_not_shutting_down()?;
+        utils::check_memory_soft_limit()?;
+        oom::install_hook();
+        // Threads not spawned by this crate count as the root, like the main thread
+        let spawn_depth =
+            CURRENT_CONFIG.with(|config| config.borrow().as_ref().map_or(0, |config| config.spawn_depth)) + 1;
+        utils::check_spawn_depth(spawn_depth)?;
+        if self.pool_size.is_some() {
+            pool::check_not_draining()?;
+        }
+
+        if let Some((deadline, grace)) = self.soft_deadline {
+            let hard_deadline = deadline.saturating_add(grace);
+            self.max_lifetime = Some(
+                self.max_lifetime
+                    .map_or(hard_deadline, |lifetime| lifetime.min(hard_deadline)),
+            );
+        }
+        if !terminable {
+            self.max_lifetime = None;
+            self.startup_timeout = None;
+        }
+
+        #[cfg(feature = "es_modules")]
+        if !self.import_scripts.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "importing scripts requires classic workers, disable the `es_modules` feature",
+            ));
+        }
+        for url in &mut self.import_scripts {
+            *url = utils::absolute_url(url)?;
+        }
+        if let Some(version) = &self.shim_version {
+            self.wasm_bindgen_shim_url = Some(utils::shim_version_url(version)?);
+        }
+        if self.worker_script_url.is_none() {
+            self.wasm_bindgen_shim_url = Some(utils::resolve_wasm_bindgen_shim_url(
+                self.wasm_bindgen_shim_url.as_deref(),
+            )?);
+        }
+
+        let cancellation_token = match self.cancellation_token.take() {
+            Some(token) => Some(token),
+            None if self.inherit_cancel => cancellation_token().map(|token| token.child_token()),
+            None => None,
+        };
+        // The soft deadline cancels the token of this thread only
+        let cancellation_token = match self.soft_deadline {
+            Some(_) => Some(cancellation_token.map_or_else(CancellationToken::new, |token| token.child_token())),
+            None => cancellation_token,
+        };
+
+        // Joined in the scope from here on, so that it cannot end before the thread has been spawned
+        let scope_member = scope_data.map(|scope| Arc::new(ScopeMember::new(scope)));
+
+        let my_state = Arc::new(ThreadState::new(
+            cancellation_token,
+            self.checkpoint.take(),
+            self.worker_index,
+            terminable,
+            scope_member.clone(),
+        ));
+        let their_state = my_state.clone();
+        if self.pool_size.is_some() {
+            pool::track(&my_state);
+        }
+
+        let my_packet: Arc<Packet<'scope, T>> = Arc::new(Packet {
+            scope: scope_member,
+            result: UnsafeCell::new(None),
+            on_discarded: Mutex::new(None),
+            _marker: PhantomData,
+        });
+        let their_packet = my_packet.clone();
+
+        // Pass `f` in `MaybeUninit` because actually that closure might *run longer than the lifetime of `F`*.
+        // See <https://github.com/rust-lang/rust/issues/101983> for more details.
+        // To prevent leaks we use a wrapper that drops its contents.
+        #[repr(transparent)]
+        struct MaybeDangling<T>(mem::MaybeUninit<T>);
+        impl<T> MaybeDangling<T> {
+            fn new(x: T) -> Self {
+                MaybeDangling(mem::MaybeUninit::new(x))
+            }
+            fn into_inner(self) -> T {
+                // SAFETY: we are always initiailized.
+                let ret = unsafe { self.0.assume_init_read() };
+                // Make sure we don't drop.
+                mem::forget(self);
+                ret
+            }
+        }
+        impl<T> Drop for MaybeDangling<T> {
+            fn drop(&mut self) {
+                // This only happens if the thread never ran, in which case its join handle has already been resolved
+                // and a panic in the drop of a captured value cannot be reported to it. Unwinding out of here could
+                // cross into JS, so the panic is logged instead.
+                // SAFETY: we are always initiailized.
+                if catch_unwind(AssertUnwindSafe(|| unsafe { self.0.assume_init_drop() })).is_err() {
+                    utils::console_warn("wasm_thread: dropping the closure of a thread that never ran panicked");
+                }
+            }
+        }
+
+        let f = MaybeDangling::new(f);
+        let main = Box::new(move || {
             // SAFETY: we constructed `f` initialized.
             let f = f.into_inner();
-            // Execute the closure and catch any panics
+            let started = utils::performance_now();
+            // Execute the closure and catch any panics, including panics while dropping its captured values, which
+            // are dropped when the call returns
             let try_result = catch_unwind(AssertUnwindSafe(|| f()));
+            // Recorded before the result is published, so that it is available once the thread is joined
+            their_state.timestamps.record_work(utils::performance_now() - started);
+            // If the thread has been terminated in the meantime, the join handle has already been resolved and the
+            // result must not be published.
+            if !their_state.try_complete() {
+                return;
+            }
+            stats::record_completed(try_result.is_err());
             // SAFETY: `their_packet` as been built just above and moved by the
             // closure (it is an Arc<...>) and `my_packet` will be stored in the
             // same `JoinInner` as this closure meaning the mutation will be
@@ -241,112 +1242,614 @@ impl Builder {
             // done.
             drop(their_packet);
             // Notify waiting handles
-            their_signal.signal();
+            their_state.signal.signal();
             // Here, the lifetime `'a` and even `'scope` can end. `main` keeps running for a bit
             // after that before returning itself.
         });
 
+        let config = WorkerConfigView {
+            name: self.name.clone(),
+            prefix: self.prefix.clone(),
+            stack_size: self.stack_size,
+            tag: self.tag,
+            worker_index: self.worker_index,
+            max_lifetime: self.max_lifetime,
+            init_extra: self.init_extra.clone(),
+            pool_size: self.pool_size,
+            arena_size: self.arena_size,
+            spawn_depth,
+        };
+
+        // The name is resolved up front, so that the handle reports the same name as the web worker
+        let thread = Thread::new(worker_name(self.name.take(), self.prefix.take()));
+        self.name = thread.name().map(String::from);
+        registry::register_token(WorkerToken::new(thread.id()), &my_state);
+
         // Erase lifetime
+        // SAFETY: the caller guarantees that borrowed data outlives the thread, either by joining it or by borrowing
+        // only for `'scope`, which does not end before all scoped threads have finished. Threads that may borrow data
+        // are never terminated, so joining cannot resolve while `main` is still running.
         let context = WebWorkerContext {
             func: mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Box<dyn FnOnce() + Send + 'static>>(main),
+            state: my_state.clone(),
+            config,
+            memory_pages: wasm32::memory_size::<0>(),
         };
 
+        stats::record_spawned();
+        #[cfg(feature = "spawn_burst_detection")]
+        spawn_burst::record_spawn();
         if is_web_worker_thread() {
-            WorkerMessage::SpawnThread(BuilderRequest { builder: self, context }).post();
+            let request = BuilderRequest {
+                builder: self,
+                context,
+                transfer: Transfer::default(),
+            };
+            WorkerMessage::SpawnThread(request).post();
         } else {
-            self.spawn_for_context(context);
-        }
-
-        if let Some(scope) = &my_packet.scope {
-            scope.increment_num_running_threads();
+            rate_limit::schedule(BuilderRequest {
+                builder: self,
+                context,
+                transfer,
+            });
         }
 
         Ok(JoinInner {
-            signal: my_signal,
+            thread,
+            state: my_state,
             packet: my_packet,
+            #[cfg(feature = "stepped_executor")]
+            stepper: None,
         })
     }
 
-    unsafe fn spawn_for_context(self, ctx: WebWorkerContext) {
+    unsafe fn spawn_for_context(mut self, ctx: WebWorkerContext, values: Transfer) {
+        // The thread may have been aborted while its spawn request was relayed or queued
+        if ctx.state.termination().is_some() {
+            pool::settle(&ctx.state);
+            return;
+        }
+
+        if let Some(integrity) = self.script_integrity.take() {
+            return self.spawn_verified(integrity, ctx, values);
+        }
+
         let Builder {
             name,
             prefix,
             worker_script_url,
             wasm_bindgen_shim_url,
+            max_lifetime,
+            startup_timeout,
+            soft_deadline,
+            module_bytes,
+            release_module_after_init,
+            pool_size,
+            affinity,
+            tag,
+            import_scripts,
+            init_extra,
+            critical_cleanups,
+            worker_type,
+            credentials,
             ..
         } = self;
 
         // Get worker script as URL encoded blob
-        let script = worker_script_url.unwrap_or(get_worker_script(wasm_bindgen_shim_url));
+        // The bundled script has the type matching the `es_modules` feature
+        let worker_type = worker_type.filter(|_| worker_script_url.is_some());
+        let script = worker_script_url.unwrap_or_else(|| {
+            // The shim URL is resolved when spawning unless a worker script is set
+            let wasm_bindgen_shim_url = wasm_bindgen_shim_url.expect("wasm_bindgen shim URL is not resolved");
+            utils::worker_script(wasm_bindgen_shim_url, &import_scripts)
+        });
+
+        let name = worker_name(name, prefix);
 
         // Todo: figure out how to set stack size
         let mut options = WorkerOptions::new();
-        match (name, prefix) {
-            (Some(name), Some(prefix)) => {
-                options.name(&format!("{}:{}", prefix, name));
-            }
-            (Some(name), None) => {
-                options.name(&name);
+        if let Some(name) = &name {
+            options.name(name);
+        }
+
+        if let Some(credentials) = credentials {
+            options.set_credentials(match credentials {
+                WorkerCredentials::Omit => RequestCredentials::Omit,
+                WorkerCredentials::SameOrigin => RequestCredentials::SameOrigin,
+                WorkerCredentials::Include => RequestCredentials::Include,
+            });
+        }
+
+        #[cfg(feature = "es_modules")]
+        let default_type = WorkerKind::Module;
+        #[cfg(not(feature = "es_modules"))]
+        let default_type = WorkerKind::Classic;
+        match worker_type.unwrap_or(default_type) {
+            WorkerKind::Module => {
+                #[cfg(feature = "es_modules")]
+                utils::load_module_workers_polyfill();
+                options.type_(WorkerType::Module);
             }
-            (None, Some(prefix)) => {
-                let random = (js_sys::Math::random() * 10e10) as u64;
-                options.name(&format!("{}:{}", prefix, random));
+            WorkerKind::Classic => {
+                options.type_(WorkerType::Classic);
             }
-            (None, None) => {}
+        }
+
+        // Spawn the worker, unless a pooled worker with the same settings is idle
+        let pool = pool_size.map(|capacity| {
+            let key = PoolKey {
+                script: script.clone(),
+                worker_type,
+                credentials,
+            };
+            (key, capacity)
+        });
+        let reused = pool.as_ref().and_then(|(key, _)| pool::take(key, affinity));
+        let (id, worker) = match &reused {
+            // A pooled worker keeps its identifier across threads
+            Some((id, worker)) => (*id, worker.clone()),
+            None => (
+                WorkerId::next(),
+                Rc::new(Worker::new_with_options(script.as_str(), &options).unwrap()),
+            ),
         };
+        ctx.state.timestamps.record_created();
 
-        #[cfg(feature = "es_modules")]
-        {
-            utils::load_module_workers_polyfill();
-            options.type_(WorkerType::Module);
+        // The bootstrap also applies the name to the worker global scope, in case the worker options were ignored
+        let init = js_sys::Object::new();
+        if let Some(name) = &name {
+            js_sys::Reflect::set(&init, &"name".into(), &name.into()).unwrap();
         }
-        #[cfg(not(feature = "es_modules"))]
-        {
-            options.type_(WorkerType::Classic);
+        if let Some(extra) = &init_extra {
+            js_sys::Reflect::set(&init, &"extra".into(), &extra.into()).unwrap();
         }
+        if release_module_after_init {
+            js_sys::Reflect::set(&init, &"release_module".into(), &JsValue::TRUE).unwrap();
+        }
+        if pool.is_some() {
+            js_sys::Reflect::set(&init, &"pooled".into(), &JsValue::TRUE).unwrap();
+        }
+        let info = WorkerInfo::new(id, name, tag);
+        registry::register(info.clone());
 
-        // Spawn the worker
-        let worker = Rc::new(Worker::new_with_options(script.as_str(), &options).unwrap());
+        // The worker handlers are owned by this slot instead of being leaked, so that they can be released once the
+        // worker is done. Together with the worker reference, this is the only state that outlives this function, so
+        // the worker stays alive until the thread completes regardless of what happens to its join handle.
+        let resources: Rc<RefCell<Option<WorkerResources>>> = Rc::default();
 
-        // Make copy and keep a reference in callback handler so that GC does not despawn worker
-        let mut their_worker = Some(worker.clone());
+        // Arm the deadlines, which are disarmed again once the thread completes
+        let mut timers = Vec::new();
+        let mut arm = |timeout: Duration, on_timeout: Closure<dyn FnMut()>| {
+            let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+            let timer_id = utils::set_timeout(on_timeout.as_ref().unchecked_ref(), millis);
+            timers.push((timer_id, on_timeout));
+        };
+        if let Some((deadline, _)) = soft_deadline {
+            let state = ctx.state.clone();
+            let on_timeout = Closure::wrap(Box::new(move || {
+                if let Some(token) = &state.cancellation_token {
+                    token.cancel();
+                }
+            }) as Box<dyn FnMut()>);
+            arm(deadline, on_timeout);
+        }
+        if let Some(lifetime) = max_lifetime {
+            let state = ctx.state.clone();
+            let resources = resources.clone();
+            let on_timeout = Closure::wrap(Box::new(move || {
+                if state.try_terminate(Termination::LifetimeExceeded) {
+                    WorkerResources::release(&resources, true);
+                }
+            }) as Box<dyn FnMut()>);
+            arm(lifetime, on_timeout);
+        }
+        if let Some(timeout) = startup_timeout {
+            let state = ctx.state.clone();
+            let resources = resources.clone();
+            let on_timeout = Closure::wrap(Box::new(move || {
+                if !state.timestamps.has_started() && state.try_terminate(Termination::StartupTimedOut) {
+                    WorkerResources::release(&resources, true);
+                }
+            }) as Box<dyn FnMut()>);
+            arm(timeout, on_timeout);
+        }
 
-        let callback = Closure::wrap(Box::new(move |x: &web_sys::MessageEvent| {
+        let their_resources = resources.clone();
+        let their_state = ctx.state.clone();
+        let mut their_pool = pool;
+        let on_message = Closure::wrap(Box::new(move |x: &web_sys::MessageEvent| {
             // All u32 bits map to f64 mantisa so it's safe to cast like that
             let req = Box::from_raw(x.data().as_f64().unwrap() as u32 as *mut WorkerMessage);
+            // Move the message out of its box, which frees it before the message is acted upon
+            let msg = *req;
 
-            match *req {
-                WorkerMessage::SpawnThread(builder) => {
-                    builder.spawn();
+            match msg {
+                WorkerMessage::SpawnThread(request) => {
+                    rate_limit::schedule(request);
+                }
+                WorkerMessage::Wake(waker) => {
+                    waker.wake();
+                }
+                WorkerMessage::TerminateAll => {
+                    terminate_all_workers();
+                }
+                WorkerMessage::TerminateCancellable => {
+                    terminate_cancellable_workers();
+                }
+                WorkerMessage::Abort(state) => {
+                    ThreadState::release_aborted(&state);
+                }
+                WorkerMessage::Ready => {
+                    their_state.timestamps.record_ready();
+                }
+                WorkerMessage::Started => {
+                    their_state.timestamps.record_started();
                 }
                 WorkerMessage::ThreadComplete => {
-                    // Drop worker reference so it can be cleaned up by GC
-                    their_worker.take();
+                    their_state.timestamps.record_completed();
+                    let worker = their_resources
+                        .borrow()
+                        .as_ref()
+                        .map(|resources| resources.worker.clone());
+                    // The thread has already released its packet before posting this message, and the message itself
+                    // has been freed above
+                    WorkerResources::release(&their_resources, false);
+                    // A pooled worker does not close itself, and waits for its next job instead
+                    if let (Some((key, capacity)), Some(worker)) = (their_pool.take(), worker) {
+                        pool::recycle(key, affinity, capacity, id, worker);
+                    }
+                    pool::settle(&their_state);
                 }
             };
         }) as Box<dyn FnMut(&web_sys::MessageEvent)>);
-        worker.set_onmessage(Some(callback.as_ref().unchecked_ref()));
 
-        // TODO: cleanup this leak somehow
-        callback.forget();
+        // A trap, e.g. a panic with `panic=abort`, is reported as an error event and the thread never completes
+        let state = ctx.state.clone();
+        let their_resources = resources.clone();
+        let on_error = Closure::wrap(Box::new(move |event: &web_sys::Event| {
+            // Release the borrow before calling the handler, which may replace itself
+            let handler = GLOBAL_ERROR_HANDLER.with(|handler| handler.borrow().clone());
+            if let Some(handler) = handler {
+                handler(info.clone(), event.into());
+            }
+
+            let reason = if state.out_of_memory.load(Ordering::Acquire) {
+                Termination::OutOfMemory
+            } else {
+                Termination::Trapped
+            };
+            if state.try_terminate(reason) {
+                // The error has been handled by failing the join handle
+                event.prevent_default();
+                WorkerResources::release(&their_resources, true);
+            }
+        }) as Box<dyn FnMut(&web_sys::Event)>);
+
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        *resources.borrow_mut() = Some(WorkerResources {
+            id,
+            state: ctx.state.clone(),
+            worker: worker.clone(),
+            timers,
+            critical_cleanups,
+            _on_message: on_message,
+            _on_error: on_error,
+        });
+        LIVE_RESOURCES.with(|live| live.borrow_mut().insert(id, resources.clone()));
 
+        let state = ctx.state.clone();
         let ctx_ptr = Box::into_raw(Box::new(ctx));
 
-        // Pack shared wasm (module and memory) and work as a single JS array
-        let init = js_sys::Array::new();
-        init.push(&wasm_bindgen::module());
-        init.push(&wasm_bindgen::memory());
-        init.push(&JsValue::from(ctx_ptr as u32));
-
-        // Send initialization message
-        match worker.post_message(&init) {
-            Ok(()) => Ok(worker),
-            Err(e) => {
-                drop(Box::from_raw(ctx_ptr));
-                Err(e)
+        // Pack shared wasm (module and memory) and work into the initialization message
+        let transfer = js_sys::Array::new();
+        if let Some(stream) = &values.stream {
+            js_sys::Reflect::set(&init, &"stream".into(), stream).unwrap();
+            transfer.push(stream);
+        }
+        if !values.ports.is_empty() {
+            let ports: js_sys::Array = values.ports.iter().collect();
+            js_sys::Reflect::set(&init, &"ports".into(), &ports).unwrap();
+            for port in &values.ports {
+                transfer.push(port);
+            }
+        }
+        let module = match module_bytes {
+            // A reused worker has been instantiated already
+            _ if reused.is_some() => JsValue::UNDEFINED,
+            Some(ModuleBytes(bytes)) => {
+                // Copy into a standalone buffer, which can be transferred rather than cloned
+                let bytes = js_sys::Uint8Array::from(&bytes[..]);
+                transfer.push(&bytes.buffer());
+                bytes.into()
+            }
+            None => wasm_bindgen::module(),
+        };
+
+        // Depending on how the wasm was loaded, the module may still be compiling. The worker must not receive it
+        // before it is ready, so the initialization message is only sent once it has resolved. The test runner only
+        // starts tests once the module has been compiled, and `wasm_bindgen::module` reads it from the private state
+        // of the `wasm_bindgen` shim, so tests cannot cover the promise.
+        match module.dyn_into::<js_sys::Promise>() {
+            Ok(promise) => {
+                let on_settled = Closure::once_into_js(move |settled: js_sys::Array| {
+                    let settled = settled.get(0);
+                    let status = js_sys::Reflect::get(&settled, &"status".into()).unwrap_or(JsValue::UNDEFINED);
+                    if status.as_string().as_deref() != Some("fulfilled") && state.try_terminate(Termination::Trapped) {
+                        WorkerResources::release(&resources, true);
+                    }
+
+                    if state.termination().is_some() {
+                        // The worker never received the context, so the thread will never run
+                        drop(Box::from_raw(ctx_ptr));
+                    } else {
+                        let module = js_sys::Reflect::get(&settled, &"value".into()).unwrap();
+                        Self::post_init(&worker, &init, &module, &transfer, ctx_ptr, &state, &resources);
+                    }
+                });
+                // Unlike `then` with separate handlers, the callback is called exactly once, which frees it
+                utils::then_with_function(
+                    &js_sys::Promise::all_settled(&js_sys::Array::of1(&promise)),
+                    &on_settled,
+                );
+            }
+            Err(module) => Self::post_init(&worker, &init, &module, &transfer, ctx_ptr, &state, &resources),
+        }
+    }
+
+    // Sends the initialization message to a worker, which starts running the thread
+    unsafe fn post_init(
+        worker: &Worker,
+        init: &js_sys::Object,
+        module: &JsValue,
+        transfer: &js_sys::Array,
+        ctx_ptr: *mut WebWorkerContext,
+        state: &ThreadState,
+        resources: &Rc<RefCell<Option<WorkerResources>>>,
+    ) {
+        js_sys::Reflect::set(init, &"module".into(), module).unwrap();
+        js_sys::Reflect::set(init, &"memory".into(), &wasm_bindgen::memory()).unwrap();
+        js_sys::Reflect::set(init, &"work".into(), &JsValue::from(ctx_ptr as u32)).unwrap();
+
+        // Posting fails as a whole if any value cannot be transferred, e.g. because it has been transferred already. No
+        // value is transferred then, and the thread never runs.
+        let posting = utils::performance_now();
+        let posted = worker.post_message_with_transfer(init, transfer);
+        state.timestamps.record_serialization(posting);
+        if let Err(error) = posted {
+            drop(Box::from_raw(ctx_ptr));
+            utils::console_warn(&format!(
+                "wasm_thread: failed to send the initialization message to a web worker: {:?}",
+                error
+            ));
+            if state.try_terminate(Termination::Trapped) {
+                WorkerResources::release(resources, true);
+            }
+        }
+    }
+}
+
+/// Reason a thread was stopped before it could run to completion.
+///
+/// Joining a terminated thread yields an `Err` whose payload is a boxed `Termination`, which can be recovered by
+/// downcasting the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Termination {
+    /// The thread was still running when the lifetime set by [Builder::max_lifetime] ran out.
+    LifetimeExceeded,
+    /// The thread was still running when [terminate_all_workers] was called.
+    Terminated,
+    /// The thread was aborted with [JoinHandle::abort], or by [try_join_all] because another thread failed.
+    Aborted,
+    /// The web worker failed with an uncaught error, e.g. because the thread panicked with `panic=abort` or because
+    /// the worker could not load the wasm module, or the values to transfer into it could not be transferred.
+    ///
+    /// As with [Builder::max_lifetime], no destructors are run and locks held by the thread are never released.
+    Trapped,
+    /// The worker script could not be fetched, or did not match the integrity metadata set with
+    /// [Builder::script_integrity]. The thread never ran.
+    IntegrityCheckFailed,
+    /// The thread had not started running when the timeout set by [Builder::startup_timeout] ran out.
+    StartupTimedOut,
+    /// The thread trapped because an allocation failed.
+    ///
+    /// This is detected with an allocation error hook, which is only effective as long as the application does not
+    /// replace it with [std::alloc::set_alloc_error_hook].
+    OutOfMemory,
+}
+
+impl Termination {
+    fn from_status(status: u8) -> Option<Self> {
+        match status {
+            ThreadState::LIFETIME_EXCEEDED => Some(Self::LifetimeExceeded),
+            ThreadState::TRAPPED => Some(Self::Trapped),
+            ThreadState::TERMINATED => Some(Self::Terminated),
+            ThreadState::ABORTED => Some(Self::Aborted),
+            ThreadState::INTEGRITY_CHECK_FAILED => Some(Self::IntegrityCheckFailed),
+            ThreadState::STARTUP_TIMED_OUT => Some(Self::StartupTimedOut),
+            ThreadState::OUT_OF_MEMORY => Some(Self::OutOfMemory),
+            _ => None,
+        }
+    }
+
+    fn status(self) -> u8 {
+        match self {
+            Self::LifetimeExceeded => ThreadState::LIFETIME_EXCEEDED,
+            Self::Trapped => ThreadState::TRAPPED,
+            Self::Terminated => ThreadState::TERMINATED,
+            Self::Aborted => ThreadState::ABORTED,
+            Self::IntegrityCheckFailed => ThreadState::INTEGRITY_CHECK_FAILED,
+            Self::StartupTimedOut => ThreadState::STARTUP_TIMED_OUT,
+            Self::OutOfMemory => ThreadState::OUT_OF_MEMORY,
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LifetimeExceeded => f.write_str("thread exceeded its maximum lifetime"),
+            Self::Trapped => f.write_str("thread trapped"),
+            Self::Terminated => f.write_str("thread was terminated"),
+            Self::Aborted => f.write_str("thread was aborted"),
+            Self::IntegrityCheckFailed => f.write_str("worker script failed its integrity check"),
+            Self::StartupTimedOut => f.write_str("thread did not start in time"),
+            Self::OutOfMemory => f.write_str("thread ran out of memory"),
+        }
+    }
+}
+
+impl std::error::Error for Termination {}
+
+// State shared between the spawned thread, its join handle and the main thread,
+// which owns the web worker. It does not depend on the result type so that the
+// main thread can terminate the thread without knowing `T`.
+//
+// The thread and the main thread race to move the status out of `RUNNING`. If
+// the thread wins, it publishes its result into the `Packet`. If the main thread
+// wins, the result is discarded and joining yields the `Termination` reason.
+// Either way the signal is raised exactly once.
+//
+// `paused` is a gate that the thread checks in `yield_now`. It is 1 while the
+// thread should not make progress.
+pub(crate) struct ThreadState {
+    status: AtomicU8,
+    // Raised once the thread has finished. A pooled worker does not reuse it for its next thread, because the join
+    // handle, waiters and tokens of the finished thread keep checking it after the worker has moved on.
+    signal: Signal,
+    paused: AtomicU32,
+    cancellation_token: Option<CancellationToken>,
+    // Threads that may borrow data must not be terminated, because the data could otherwise be dropped while the
+    // thread is still accessing it
+    terminable: bool,
+    // Label set by the thread with `set_current_activity`
+    activity: Mutex<Option<String>>,
+    // Type-erased slot of the `Checkpoint` attached to the thread
+    checkpoint: Option<Arc<dyn Any + Send + Sync>>,
+    // Index set with `Builder::worker_index`
+    worker_index: Option<usize>,
+    // Milestones recorded by the main thread
+    timestamps: Timestamps,
+    // Membership in the scope the thread was spawned in, which a terminated thread leaves without dropping its packet
+    scope: Option<Arc<ScopeMember>>,
+    // Set by the thread when an allocation failed, right before it traps
+    out_of_memory: AtomicBool,
+}
+
+impl ThreadState {
+    const RUNNING: u8 = 0;
+    const COMPLETED: u8 = 1;
+    const LIFETIME_EXCEEDED: u8 = 2;
+    const TRAPPED: u8 = 3;
+    const TERMINATED: u8 = 4;
+    const ABORTED: u8 = 5;
+    const INTEGRITY_CHECK_FAILED: u8 = 6;
+    const STARTUP_TIMED_OUT: u8 = 7;
+    const OUT_OF_MEMORY: u8 = 8;
+
+    fn new(
+        cancellation_token: Option<CancellationToken>,
+        checkpoint: Option<Arc<dyn Any + Send + Sync>>,
+        worker_index: Option<usize>,
+        terminable: bool,
+        scope: Option<Arc<ScopeMember>>,
+    ) -> Self {
+        Self {
+            status: AtomicU8::new(Self::RUNNING),
+            signal: Signal::new(),
+            paused: AtomicU32::new(0),
+            cancellation_token,
+            terminable,
+            activity: Mutex::new(None),
+            checkpoint,
+            worker_index,
+            timestamps: Timestamps::default(),
+            scope,
+            out_of_memory: AtomicBool::new(false),
+        }
+    }
+
+    // Claims the right to publish the thread result. Fails if the thread has
+    // already been terminated.
+    fn try_complete(&self) -> bool {
+        self.status
+            .compare_exchange(Self::RUNNING, Self::COMPLETED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    // Marks a running thread as terminated and wakes up its join handle. Fails if
+    // the thread has already completed or been terminated.
+    fn try_terminate(&self, reason: Termination) -> bool {
+        let terminated = self
+            .status
+            .compare_exchange(Self::RUNNING, reason.status(), Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if terminated {
+            stats::record_terminated(reason);
+            // The web worker is gone, so the packet it holds is never dropped. The thread did not complete, which
+            // makes the scope panic like for a thread that panicked.
+            if let Some(scope) = &self.scope {
+                scope.release(true);
+            }
+            self.signal.signal();
+        }
+        terminated
+    }
+
+    // Aborts a running thread and terminates its web worker, if it can be terminated. The join handle resolves
+    // immediately, while the worker is terminated by the main thread.
+    fn abort(self: &Arc<Self>) {
+        if !self.terminable || !self.try_terminate(Termination::Aborted) {
+            return;
+        }
+
+        if is_web_worker_thread() {
+            WorkerMessage::Abort(self.clone()).post();
+        } else {
+            Self::release_aborted(self);
+        }
+    }
+
+    // Terminates the web worker of an aborted thread on the main thread. If the worker has not been created yet, it
+    // never will be.
+    fn release_aborted(state: &Arc<Self>) {
+        let slot = LIVE_RESOURCES.with(|live| {
+            live.borrow()
+                .values()
+                .find(|slot| {
+                    slot.borrow()
+                        .as_ref()
+                        .map_or(false, |resources| Arc::ptr_eq(&resources.state, state))
+                })
+                .cloned()
+        });
+        if let Some(slot) = slot {
+            WorkerResources::release(&slot, true);
+        }
+    }
+
+    fn termination(&self) -> Option<Termination> {
+        Termination::from_status(self.status.load(Ordering::Acquire))
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.paused.store(paused as u32, Ordering::Release);
+
+        if !paused {
+            unsafe {
+                wasm32::memory_atomic_notify(&self.paused as *const AtomicU32 as *mut i32, i32::MAX as u32);
+            }
+        }
+    }
+
+    // Blocks the calling thread for as long as the pause gate is closed.
+    fn wait_while_paused(&self) {
+        while self.paused.load(Ordering::Acquire) == 1 {
+            unsafe {
+                wasm32::memory_atomic_wait32(&self.paused as *const AtomicU32 as *mut i32, 1, -1);
             }
         }
-        .unwrap();
     }
 }
 
@@ -358,8 +1861,10 @@ impl Builder {
 // An Arc to the packet is stored into a `JoinInner` which in turns is placed
 // in `JoinHandle`.
 struct Packet<'scope, T> {
-    scope: Option<Arc<ScopeData>>,
+    scope: Option<Arc<ScopeMember>>,
     result: UnsafeCell<Option<Result<T>>>,
+    // Receives the result if nobody consumed it, see `JoinHandle::on_discarded`
+    on_discarded: Mutex<Option<DiscardedResultCallback<'scope, T>>>,
     _marker: PhantomData<Option<&'scope ScopeData>>,
 }
 
@@ -374,7 +1879,8 @@ impl<'scope, T> Drop for Packet<'scope, T> {
         // If this packet was for a thread that ran in a scope, the thread
         // panicked, and nobody consumed the panic payload, we make sure
         // the scope function will panic.
-        let unhandled_panic = matches!(self.result.get_mut(), Some(Err(_)));
+        let on_discarded = self.on_discarded.get_mut().unwrap().take();
+        let unhandled_panic = matches!(self.result.get_mut(), Some(Err(_))) && on_discarded.is_none();
         // Drop the result without causing unwinding.
         // This is only relevant for threads that aren't join()ed, as
         // join() will take the `result` and set it to None, such that
@@ -385,8 +1891,12 @@ impl<'scope, T> Drop for Packet<'scope, T> {
         // (And even if we tried to handle it somehow, we'd also need to handle
         // the case where the panic payload we get out of it also panics on
         // drop, and so on. See issue #86027.)
+        // The same applies to the discarded result callback, which takes the result instead.
         if let Err(_) = catch_unwind(AssertUnwindSafe(|| {
-            *self.result.get_mut() = None;
+            let result = self.result.get_mut().take();
+            if let (Some(result), Some(on_discarded)) = (result, on_discarded) {
+                on_discarded(result);
+            }
         })) {
             panic!("thread result panicked on drop");
         }
@@ -396,48 +1906,343 @@ impl<'scope, T> Drop for Packet<'scope, T> {
             // that can use 'scope, mark the thread as 'finished'.
             // It's important we only do this after the `result` has been dropped,
             // since dropping it might still use things it borrowed from 'scope.
-            scope.decrement_num_running_threads(unhandled_panic);
+            scope.release(unhandled_panic);
+        }
+    }
+}
+
+// Combines the name and prefix set on a builder into the name of the web worker
+fn worker_name(name: Option<String>, prefix: Option<String>) -> Option<String> {
+    match (name, prefix) {
+        (Some(name), Some(prefix)) => Some(format!("{}:{}", prefix, name)),
+        (Some(name), None) => Some(name),
+        (None, Some(prefix)) => {
+            let random = (js_sys::Math::random() * 10e10) as u64;
+            Some(format!("{}:{}", prefix, random))
         }
+        (None, None) => None,
     }
 }
 
 /// Inner representation for JoinHandle
 pub(crate) struct JoinInner<'scope, T> {
+    thread: Thread,
     packet: Arc<Packet<'scope, T>>,
-    signal: Arc<Signal>,
+    state: Arc<ThreadState>,
+    // Set for threads spawned with `Builder::spawn_stepped`
+    #[cfg(feature = "stepped_executor")]
+    stepper: Option<Arc<stepped::Stepper>>,
 }
 
 impl<'scope, T> JoinInner<'scope, T> {
-    pub fn join(mut self) -> Result<T> {
-        self.signal.wait();
-        Arc::get_mut(&mut self.packet).unwrap().result.get_mut().take().unwrap()
+    pub fn join(self) -> Result<T> {
+        // Fail early to avoid flaky panics that depend on execution time
+        if !is_web_worker_thread() {
+            panic!("blocking join is not allowed on the main thread, use join_async instead");
+        }
+
+        #[cfg(feature = "deadlock_detection")]
+        let _wait = match deadlock::begin_wait(&self.state) {
+            Ok(wait) => wait,
+            Err(deadlock) => return Err(Box::new(deadlock)),
+        };
+
+        self.state.signal.wait();
+        self.take_result()
     }
 
-    pub async fn join_async(mut self) -> Result<T> {
-        self.signal.wait_async().await;
-        Arc::get_mut(&mut self.packet).unwrap().result.get_mut().take().unwrap()
+    pub async fn join_async(self) -> Result<T> {
+        self.state.signal.wait_async().await;
+        self.take_result()
+    }
+
+    // Must only be called once the signal has been raised or interrupted.
+    fn take_result(self) -> Result<T> {
+        // The thread may still be running, so the packet must not be touched
+        if !self.state.signal.is_signaled() {
+            return Err(Box::new(JoinInterrupted));
+        }
+
+        // SAFETY: the signal is raised only after the thread has published its
+        // result (and will no longer touch the packet) or after it has been
+        // terminated, in which case it can never publish one. A terminated
+        // thread may keep its reference to the packet forever, so we cannot rely
+        // on `Arc::get_mut` here.
+        match unsafe { (*self.packet.result.get()).take() } {
+            Some(result) => result,
+            None => Err(Box::new(
+                self.state.termination().expect("thread signalled without a result"),
+            )),
+        }
+    }
+}
+
+/// Interrupts joining a thread, see [JoinHandle::interrupter].
+#[derive(Clone)]
+pub struct JoinInterrupter(Arc<ThreadState>);
+
+impl JoinInterrupter {
+    /// Makes a pending or future join on the thread return an error with a [JoinInterrupted] payload, unless the
+    /// thread has already finished.
+    ///
+    /// This wakes a thread blocked in [JoinHandle::join] as well as a pending [JoinHandle::join_async]. The joined
+    /// thread keeps running detached.
+    pub fn interrupt(&self) {
+        self.0.signal.interrupt();
+    }
+}
+
+impl fmt::Debug for JoinInterrupter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinInterrupter").finish_non_exhaustive()
+    }
+}
+
+/// Waits for a thread to finish without taking its result, see [JoinHandle::detach_wait].
+#[derive(Clone)]
+pub struct JoinWaiter(Arc<ThreadState>);
+
+impl JoinWaiter {
+    /// Waits asynchronously until the thread has finished, or until a join on it was interrupted with a
+    /// [JoinInterrupter].
+    ///
+    /// Dropping the future only stops waiting: the thread keeps running and its result stays in the join handle. A new
+    /// future can be created at any time.
+    pub async fn wait(&self) {
+        self.0.signal.wait_async().await;
+    }
+
+    /// Returns `true` if the thread has finished, so that [JoinHandle::try_join] returns its result.
+    pub fn is_finished(&self) -> bool {
+        self.0.signal.is_signaled()
+    }
+}
+
+impl fmt::Debug for JoinWaiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinWaiter")
+            .field("finished", &self.is_finished())
+            .finish()
     }
 }
 
+/// Payload of the error returned by joining a thread after a [JoinInterrupter] was used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinInterrupted;
+
+impl fmt::Display for JoinInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("join was interrupted")
+    }
+}
+
+impl std::error::Error for JoinInterrupted {}
+
 /// An owned permission to join on a thread (block on its termination).
+///
+/// Dropping a `JoinHandle` detaches the thread: its web worker keeps running until the thread completes, but there is
+/// no longer a way to obtain its result.
+///
+/// The handle only holds the state shared with the thread, so it is `Send` if `T` is, and can be moved into other
+/// tasks or threads and held across await points. The `Worker` object, which cannot leave the main thread, is kept in a
+/// main thread registry keyed by the id of the worker until the thread finishes or is terminated.
 pub struct JoinHandle<T>(JoinInner<'static, T>);
 
 impl<T> JoinHandle<T> {
     /// Extracts a handle to the underlying thread.
     pub fn thread(&self) -> &Thread {
-        unimplemented!();
-        //&self.0.thread
+        &self.0.thread
     }
 
     /// Waits for the associated thread to finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, because blocking waits are not allowed there. Use [Self::join_async] instead.
+    ///
+    /// # Deadlocks
+    ///
+    /// Threads that join each other in a cycle block forever. With the `deadlock_detection` feature, the join that
+    /// closes the cycle instead fails with a `DeadlockDetected` payload.
     pub fn join(self) -> Result<T> {
         self.0.join()
     }
 
+    /// Waits for the associated thread to finish like [Self::join], and classifies the error if it failed.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, because blocking waits are not allowed there. Use [Self::join_detailed_async]
+    /// instead.
+    pub fn join_detailed(self) -> std::result::Result<T, JoinError> {
+        self.join().map_err(JoinError::from_payload)
+    }
+
+    /// Waits for the associated thread to finish asynchronously like [Self::join_async], and classifies the error if
+    /// it failed.
+    pub async fn join_detailed_async(self) -> std::result::Result<T, JoinError> {
+        self.join_async().await.map_err(JoinError::from_payload)
+    }
+
     /// Waits for the associated thread to finish asynchronously.
+    ///
+    /// The future does not poll in a loop: it is woken by the thread once it finishes, so the main thread keeps
+    /// processing events in the meantime and no explicit yielding is needed.
     pub async fn join_async(self) -> Result<T> {
         self.0.join_async().await
     }
+
+    /// Waits asynchronously for the associated thread to finish, and returns its result together with how long it ran.
+    ///
+    /// The duration is measured by the thread itself, from when it started running its closure until the closure
+    /// returned, so it excludes creating the web worker and instantiating the module, see [Self::timings] for those.
+    /// It is zero for threads that were terminated before their closure returned.
+    pub async fn join_timed(self) -> (Result<T>, Duration) {
+        let state = self.0.state.clone();
+        let result = self.join_async().await;
+        (result, state.timestamps.work_duration().unwrap_or_default())
+    }
+
+    /// Waits at most `timeout` for the associated thread to finish, and falls back to `default` otherwise.
+    ///
+    /// If the thread finishes in time, its result is returned without a handle. Otherwise `default` is returned
+    /// together with the handle, and the thread keeps running in the background, so it can still be joined later. This
+    /// suits optimistic UIs, which show a placeholder until the result is ready.
+    pub async fn join_or_default(self, timeout: Duration, default: T) -> (Result<T>, Option<JoinHandle<T>>) {
+        let timer = Arc::new(Signal::new());
+        let their_timer = timer.clone();
+        // The timer is not cleared if the thread wins, as the callback frees itself only when it is called
+        let on_timeout = Closure::once_into_js(move || their_timer.signal());
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        utils::set_timeout(on_timeout.unchecked_ref(), millis);
+
+        let finished = {
+            let finished = pin!(self.0.state.signal.wait_async());
+            let timed_out = pin!(timer.wait_async());
+            matches!(future::select(finished, timed_out).await, Either::Left(_))
+        };
+        if finished {
+            (self.0.take_result(), None)
+        } else {
+            (Ok(default), Some(self))
+        }
+    }
+
+    /// Sets a callback that receives the result of the thread if it is never consumed, e.g. because the handle is
+    /// dropped without joining.
+    ///
+    /// The callback receives the value returned by the thread, or the panic payload if it panicked, so important
+    /// results can at least be logged. It runs on whichever thread releases the result last: the thread itself if the
+    /// handle was dropped before it completed, and the thread dropping the handle otherwise. It is not called for
+    /// threads that are terminated, as they have no result, nor once the handle is joined. Setting another callback
+    /// replaces the previous one.
+    pub fn on_discarded<F>(self, callback: F) -> JoinHandle<T>
+    where
+        F: FnOnce(Result<T>) + Send + 'static,
+    {
+        *self.0.packet.on_discarded.lock_spin().unwrap() = Some(Box::new(callback));
+        self
+    }
+
+    /// Aborts the associated thread by terminating its web worker.
+    ///
+    /// A pending or later join resolves right away with an error with a [Termination::Aborted] payload. A thread whose
+    /// web worker has not been sent its closure yet never runs, and the closure is dropped on the main thread. Aborting
+    /// a thread that has already finished does nothing, so joining still yields its result.
+    ///
+    /// # Warning
+    ///
+    /// Termination is abrupt, see [Builder::max_lifetime]. Threads spawned with [Builder::spawn_unchecked] may borrow
+    /// data, so they are never terminated, and aborting them does nothing.
+    pub fn abort(&self) {
+        self.0.state.abort();
+    }
+
+    /// Asks the associated thread to pause.
+    ///
+    /// Pausing is cooperative: the thread only stops making progress once it reaches a call to [yield_now], and
+    /// stays blocked there until [Self::resume] is called. A thread that never yields cannot be paused.
+    pub fn pause(&self) {
+        self.0.state.set_paused(true);
+    }
+
+    /// Lets a thread paused with [Self::pause] continue.
+    pub fn resume(&self) {
+        self.0.state.set_paused(false);
+    }
+
+    /// Returns the milestones of the associated thread recorded so far.
+    pub fn timings(&self) -> ThreadTimings {
+        self.0.state.timestamps.snapshot()
+    }
+
+    /// Returns the label of what the associated thread is currently doing, as last set with [set_current_activity].
+    pub fn current_activity(&self) -> Option<String> {
+        self.0.state.activity.lock_spin().unwrap().clone()
+    }
+
+    /// Returns the value last saved to the [Checkpoint] attached with [Builder::with_checkpoint].
+    ///
+    /// Returns `None` if nothing has been saved, or if the thread has no checkpoint of type `C`.
+    pub fn last_checkpoint<C: Clone + 'static>(&self) -> Option<C> {
+        checkpoint::read_erased(self.0.state.checkpoint.as_deref()?)
+    }
+
+    /// Returns the result of the associated thread if it has finished, or gives the handle back otherwise.
+    ///
+    /// The thread is considered finished once it has completed or has been terminated, as for [Self::join_async].
+    pub fn try_join(self) -> std::result::Result<Result<T>, Self> {
+        if self.0.state.signal.is_signaled() {
+            Ok(self.0.take_result())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Returns a waiter for the associated thread, which keeps the result in this handle.
+    ///
+    /// Dropping a [Self::join_async] future drops the handle with it, which detaches the thread and loses its result.
+    /// Waiting on the returned [JoinWaiter] instead can be abandoned and resumed, without terminating the thread, and
+    /// the result is taken later with [Self::try_join] or [Self::join_async].
+    pub fn detach_wait(&self) -> JoinWaiter {
+        JoinWaiter(self.0.state.clone())
+    }
+
+    /// Returns a token referring to the thread, which can be passed to other parts of the application to wait for the
+    /// thread with [join_by_token].
+    ///
+    /// The thread can be waited for by token as long as it is running or this handle exists.
+    pub fn token(&self) -> WorkerToken {
+        WorkerToken::new(self.0.thread.id())
+    }
+
+    /// Returns an interrupter, which can make a pending join on this handle return early from another thread.
+    pub fn interrupter(&self) -> JoinInterrupter {
+        JoinInterrupter(self.0.state.clone())
+    }
+}
+
+impl JoinHandle<Box<dyn Any + Send>> {
+    /// Waits for the associated thread to finish asynchronously and downcasts its boxed result to `U`.
+    ///
+    /// The outer result is an error if the thread panicked or was terminated, exactly like [Self::join_async]. The
+    /// inner result gives back the original box if the thread returned something other than a `U`.
+    pub async fn join_boxed<U: Any>(self) -> Result<std::result::Result<Box<U>, Box<dyn Any + Send>>> {
+        self.join_async().await.map(|result| result.downcast::<U>())
+    }
+}
+
+/// Awaiting a `JoinHandle` is equivalent to [JoinHandle::join_async].
+///
+/// The resulting future is boxed and `'static`, so handles can be collected directly into combinators such as
+/// [`FuturesUnordered`](futures::stream::FuturesUnordered).
+impl<T: Send + 'static> IntoFuture for JoinHandle<T> {
+    type Output = Result<T>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.join_async())
+    }
 }
 
 impl<T> fmt::Debug for JoinHandle<T> {
@@ -446,7 +2251,186 @@ impl<T> fmt::Debug for JoinHandle<T> {
     }
 }
 
+/// Spawns a new thread and blocks until it finishes, returning its result.
+///
+/// This is a shorthand for `spawn(f).join()`, meant for quick sub-computations inside a web worker.
+///
+/// # Panics
+///
+/// Panics on the main thread, because blocking waits are not allowed there. No thread is spawned in that case.
+pub fn spawn_join<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T,
+    F: Send + 'static,
+    T: Send + 'static,
+{
+    // Fail before spawning so that the thread is not left running unobserved
+    if !is_web_worker_thread() {
+        panic!("spawn_join is not allowed on the main thread, use spawn and join_async instead");
+    }
+
+    spawn(f).join()
+}
+
+/// Waits for all threads to finish, aborting the remaining threads as soon as one of them fails.
+///
+/// On success, the results are returned in the order of `handles`. Otherwise the index and result of the first thread
+/// observed to fail is returned, and the web workers of the threads that are still running are terminated. Joining an
+/// aborted thread yields an error with a [Termination::Aborted] payload, but their handles are consumed here.
+///
+/// # Warning
+///
+/// Termination is abrupt, see [Builder::max_lifetime]. Threads spawned with [Builder::spawn_unchecked] are never
+/// terminated and keep running.
+pub async fn try_join_all<T>(handles: Vec<JoinHandle<T>>) -> std::result::Result<Vec<T>, (usize, Result<T>)> {
+    let states: Vec<_> = handles.iter().map(|handle| handle.0.state.clone()).collect();
+    let mut pending: FuturesUnordered<_> = handles
+        .into_iter()
+        .enumerate()
+        .map(|(index, handle)| async move { (index, handle.join_async().await) })
+        .collect();
+
+    let mut results: Vec<Option<T>> = states.iter().map(|_| None).collect();
+    while let Some((index, result)) = pending.next().await {
+        match result {
+            Ok(value) => results[index] = Some(value),
+            Err(payload) => {
+                for state in &states {
+                    state.abort();
+                }
+                return Err((index, Err(payload)));
+            }
+        }
+    }
+
+    Ok(results.into_iter().map(|result| result.unwrap()).collect())
+}
+
+/// Folds the results of threads into an accumulator as they finish, in completion order.
+///
+/// Each result is folded in on the calling thread as soon as its thread finishes, so cheap reductions can run
+/// incrementally on the main thread while other threads are still working, and at most one result is held at a time.
+/// Returns the error of the first thread observed to fail, in which case the remaining threads keep running detached.
+pub async fn fold_results<T, S>(handles: Vec<JoinHandle<T>>, init: S, mut f: impl FnMut(S, T) -> S) -> Result<S> {
+    let mut pending: FuturesUnordered<_> = handles.into_iter().map(JoinHandle::join_async).collect();
+
+    let mut acc = init;
+    while let Some(result) = pending.next().await {
+        acc = f(acc, result?);
+    }
+    Ok(acc)
+}
+
+/// Records a label of what the current thread is doing, which can be read through [JoinHandle::current_activity].
+///
+/// This is a lightweight aid for diagnosing hung threads. Outside of threads spawned by this crate, this does nothing.
+pub fn set_current_activity(activity: &str) {
+    if let Some(state) = CURRENT_STATE.with(|state| state.borrow().clone()) {
+        let activity = Some(activity.to_owned());
+        // Free the previous label outside of the lock, so that readers are not held up by the allocator
+        let previous = mem::replace(&mut *state.activity.lock_spin().unwrap(), activity);
+        drop(previous);
+    }
+}
+
+/// Cooperatively gives up a timeslice.
+///
+/// This is also the point at which a thread that was asked to [pause](JoinHandle::pause) suspends until it is
+/// resumed. Outside of threads spawned by this crate, this does nothing.
+pub fn yield_now() {
+    if let Some(state) = CURRENT_STATE.with(|state| state.borrow().clone()) {
+        state.wait_while_paused();
+    }
+}
+
+/// Forcibly terminates the web workers of all running threads.
+///
+/// Joining a terminated thread yields an error with a [Termination::Terminated] payload. Threads that may borrow data,
+/// i.e. scoped threads and threads spawned with [Builder::spawn_unchecked], are left running, because the data could
+/// otherwise be dropped while they are still accessing it. Idle workers kept by [Builder::pool_size] are terminated as
+/// well. When called from a web worker, the request is relayed to the main thread, and the calling thread is
+/// terminated as well.
+///
+/// # Warning
+///
+/// Termination is abrupt, see [Builder::max_lifetime]. Prefer cancelling threads cooperatively with [shutdown].
+pub fn terminate_all_workers() {
+    if is_web_worker_thread() {
+        WorkerMessage::TerminateAll.post();
+        return;
+    }
+
+    pool::drop_idle_workers();
+    terminate_live_workers(|_| true);
+}
+
+// Terminates the web workers of running threads that were spawned with a cancellation token, for `shutdown`. Relayed
+// to the main thread like `terminate_all_workers`.
+pub(crate) fn terminate_cancellable_workers() {
+    if is_web_worker_thread() {
+        WorkerMessage::TerminateCancellable.post();
+        return;
+    }
+
+    terminate_live_workers(|state| state.cancellation_token.is_some());
+}
+
+// Terminates the web workers of the running threads that may be terminated and for which `filter` holds
+fn terminate_live_workers(filter: impl Fn(&ThreadState) -> bool) {
+    // Releasing modifies the table, so take a snapshot first
+    let live: Vec<_> = LIVE_RESOURCES.with(|live| live.borrow().values().cloned().collect());
+    for slot in live {
+        let state = slot.borrow().as_ref().map(|resources| resources.state.clone());
+        if let Some(state) = state {
+            if state.terminable && filter(&state) && state.try_terminate(Termination::Terminated) {
+                WorkerResources::release(&slot, true);
+            }
+        }
+    }
+}
+
+/// Returns the cancellation token of the current thread, if it was spawned with one.
+///
+/// See [Builder::cancellation_token] and [Builder::inherit_cancel].
+pub fn cancellation_token() -> Option<CancellationToken> {
+    CURRENT_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .and_then(|state| state.cancellation_token.clone())
+    })
+}
+
+/// Returns the index of the current thread within its batch, if it was spawned with one.
+///
+/// See [Builder::worker_index] and [Builder::spawn_batch].
+pub fn current_worker_index() -> Option<usize> {
+    CURRENT_STATE.with(|state| state.borrow().as_ref().and_then(|state| state.worker_index))
+}
+
+/// Returns the configuration the current thread was spawned with, or `None` if it was not spawned by this crate.
+pub fn current_worker_config() -> Option<WorkerConfigView> {
+    CURRENT_CONFIG.with(|config| config.borrow().clone())
+}
+
+/// Returns the extra value the current thread was spawned with, if any.
+///
+/// See [Builder::init_extra].
+pub fn init_extra() -> Option<JsValue> {
+    js_sys::Reflect::get(&js_sys::global(), &"wasm_thread_init_extra".into())
+        .ok()
+        .filter(|extra| !extra.is_undefined())
+}
+
 /// Spawns a new thread, returning a JoinHandle for it.
+///
+/// The closure and its result must be `'static`, so the thread cannot borrow from the spawning thread. Use [scope] or
+/// [Builder::spawn_unchecked] to share borrowed data:
+///
+/// ```compile_fail,E0373
+/// let data = vec![1, 2, 3];
+/// wasm_thread::spawn(|| println!("{:?}", data)); // `data` might be dropped while the thread is running
+/// ```
 pub fn spawn<F, T>(f: F) -> JoinHandle<T>
 where
     F: FnOnce() -> T,
@@ -455,3 +2439,21 @@ where
 {
     Builder::new().spawn(f).expect("failed to spawn thread")
 }
+
+/// Spawns a new thread like [spawn], naming it after the `file:line` of the macro invocation.
+///
+/// A builder can be passed as the first argument, which is used instead of [Builder::new] and keeps its name if it has
+/// one. See [Builder::spawn_here].
+///
+/// # Panics
+///
+/// Panics if the thread could not be spawned, like [spawn].
+#[macro_export]
+macro_rules! spawn_here {
+    ($f:expr) => {
+        $crate::Builder::new().spawn_here($f).expect("failed to spawn thread")
+    };
+    ($builder:expr, $f:expr) => {
+        $crate::Builder::spawn_here($builder, $f).expect("failed to spawn thread")
+    };
+}
@@ -0,0 +1,281 @@
+use std::{
+    arch::wasm32,
+    cell::UnsafeCell,
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use super::utils::{is_web_worker_thread, SpinLockMutex};
+
+/// A fair mutual exclusion lock that can be awaited on the main thread and in web workers.
+///
+/// Unlike [std::sync::Mutex], locking never blocks the main thread: [Self::lock] suspends the calling task until the
+/// lock is handed over to it, and web workers that are not running async code can block in [Self::lock_blocking]
+/// instead. Waiting tasks and threads are queued, and unlocking hands the lock directly to the one that has waited
+/// longest, so no caller can be starved by others re-locking in a loop.
+///
+/// The mutex is not poisoned if a thread panics while holding the lock.
+pub struct AsyncMutex<T: ?Sized> {
+    state: Mutex<State>,
+    data: UnsafeCell<T>,
+}
+
+// The lock guarantees exclusive access to `data`, as for `std::sync::Mutex`
+unsafe impl<T: ?Sized + Send> Send for AsyncMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for AsyncMutex<T> {}
+
+#[derive(Default)]
+struct State {
+    locked: bool,
+    // Callers waiting for the lock, in the order they will receive it
+    queue: VecDeque<Arc<Waiter>>,
+}
+
+// Caller queued for a lock or permit, which is handed over to it directly. Also used by `AsyncSemaphore`.
+#[derive(Default)]
+pub(crate) struct Waiter {
+    // Set to 1 once the lock has been handed over to this waiter
+    granted: AtomicU32,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Waiter {
+    pub(crate) fn is_granted(&self) -> bool {
+        self.granted.load(Ordering::Acquire) != 0
+    }
+
+    // Hands the lock over. Only call this while the state of the lock is locked, so that a waiter giving up its place
+    // sees a consistent state, and call `wake` once it has been unlocked.
+    pub(crate) fn grant(&self) {
+        self.granted.store(1, Ordering::Release);
+    }
+
+    // Blocks the current web worker until the lock has been handed over
+    pub(crate) fn wait(&self) {
+        while !self.is_granted() {
+            unsafe {
+                wasm32::memory_atomic_wait32(&self.granted as *const AtomicU32 as *mut i32, 0, -1);
+            }
+        }
+    }
+
+    // Returns `true` if the lock has been handed over, and otherwise registers the task to be woken once it is
+    pub(crate) fn poll_granted(&self, cx: &mut Context<'_>) -> bool {
+        // The waker is stored before checking, so that a grant in between wakes it
+        *self.waker.lock_spin().unwrap() = Some(cx.waker().clone());
+        self.is_granted()
+    }
+
+    // Wakes the waiter after the lock has been handed over to it
+    pub(crate) fn wake(&self) {
+        unsafe {
+            wasm32::memory_atomic_notify(&self.granted as *const AtomicU32 as *mut i32, 1);
+        }
+        if let Some(waker) = self.waker.lock_spin().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> AsyncMutex<T> {
+    /// Creates an unlocked mutex holding `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the mutex, returning the value it holds.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> AsyncMutex<T> {
+    /// Acquires the lock, suspending the calling task until it is available.
+    ///
+    /// The caller is queued when it is first polled. Dropping the future gives up its place in the queue, or passes the
+    /// lock on if it had already been handed over.
+    pub fn lock(&self) -> AsyncMutexLock<'_, T> {
+        AsyncMutexLock {
+            mutex: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires the lock, blocking the current web worker until it is available.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, which cannot block. Use [Self::lock] there.
+    pub fn lock_blocking(&self) -> AsyncMutexGuard<'_, T> {
+        if !is_web_worker_thread() {
+            panic!("`AsyncMutex::lock_blocking` cannot be called on the main thread, use `lock` instead");
+        }
+
+        let waiter = match self.enqueue() {
+            Some(waiter) => waiter,
+            None => return AsyncMutexGuard { mutex: self },
+        };
+        waiter.wait();
+        AsyncMutexGuard { mutex: self }
+    }
+
+    /// Acquires the lock if it is available and nobody is waiting for it.
+    pub fn try_lock(&self) -> Option<AsyncMutexGuard<'_, T>> {
+        let mut state = self.state.lock_spin().unwrap();
+        if state.locked {
+            return None;
+        }
+        state.locked = true;
+        Some(AsyncMutexGuard { mutex: self })
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking as the mutex is borrowed mutably.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    // Locks the mutex if it is free, and returns `None`. Otherwise returns a new waiter at the end of the queue.
+    fn enqueue(&self) -> Option<Arc<Waiter>> {
+        let mut state = self.state.lock_spin().unwrap();
+        if !state.locked {
+            state.locked = true;
+            return None;
+        }
+        let waiter = Arc::new(Waiter::default());
+        state.queue.push_back(waiter.clone());
+        Some(waiter)
+    }
+
+    // Hands the lock to the longest waiting caller, or unlocks the mutex if nobody is waiting
+    fn unlock(&self) {
+        let next = {
+            let mut state = self.state.lock_spin().unwrap();
+            let next = state.queue.pop_front();
+            if let Some(next) = &next {
+                next.grant();
+            }
+            state.locked = next.is_some();
+            next
+        };
+        if let Some(next) = next {
+            next.wake();
+        }
+    }
+}
+
+impl<T: Default> Default for AsyncMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AsyncMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("AsyncMutex");
+        match self.try_lock() {
+            Some(guard) => debug.field("data", &&*guard),
+            None => debug.field("data", &format_args!("<locked>")),
+        };
+        debug.finish_non_exhaustive()
+    }
+}
+
+/// Future returned by [AsyncMutex::lock].
+pub struct AsyncMutexLock<'a, T: ?Sized> {
+    mutex: &'a AsyncMutex<T>,
+    // Place in the queue, once the future has been polled and the lock was taken
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl<'a, T: ?Sized> Future for AsyncMutexLock<'a, T> {
+    type Output = AsyncMutexGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<AsyncMutexGuard<'a, T>> {
+        let mutex = self.mutex;
+        let waiter = match &self.waiter {
+            Some(waiter) => waiter.clone(),
+            None => match mutex.enqueue() {
+                Some(waiter) => {
+                    self.waiter = Some(waiter.clone());
+                    waiter
+                }
+                None => return Poll::Ready(AsyncMutexGuard { mutex }),
+            },
+        };
+
+        if waiter.poll_granted(cx) {
+            self.waiter = None;
+            Poll::Ready(AsyncMutexGuard { mutex })
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for AsyncMutexLock<'_, T> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            let mut state = self.mutex.state.lock_spin().unwrap();
+            if waiter.is_granted() {
+                // The lock was handed over, but nobody will use it
+                drop(state);
+                self.mutex.unlock();
+            } else {
+                state.queue.retain(|queued| !Arc::ptr_eq(queued, &waiter));
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for AsyncMutexLock<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncMutexLock")
+            .field("queued", &self.waiter.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Grants access to the value of an [AsyncMutex] while the lock is held, and unlocks it when dropped.
+pub struct AsyncMutexGuard<'a, T: ?Sized> {
+    mutex: &'a AsyncMutex<T>,
+}
+
+// Access through a shared guard only hands out shared references
+unsafe impl<T: ?Sized + Sync> Sync for AsyncMutexGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for AsyncMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for AsyncMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for AsyncMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AsyncMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
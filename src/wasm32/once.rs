@@ -0,0 +1,165 @@
+use std::{
+    arch::wasm32,
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    task::{Poll, Waker},
+};
+
+use futures::future::poll_fn;
+
+use super::utils::{is_web_worker_thread, SpinLockMutex};
+
+/// A one-time initialization shared by all threads, like [std::sync::Once], that can be awaited on the main thread.
+///
+/// The first caller of [Self::call_once] or [Self::call_once_async] runs its closure, and all other callers, on any
+/// thread, wait until it has returned, blocking in web workers or suspending the calling task in
+/// [Self::call_once_async]. Once it has returned, calls return right away without running their closure.
+///
+/// If the closure panics, the instance is poisoned, and all pending and later calls panic as well. With `panic=abort`,
+/// a thread that traps while running the closure leaves the other callers waiting forever.
+pub struct SharedOnce {
+    state: AtomicU32,
+    waiters: Mutex<Vec<Waker>>,
+}
+
+impl SharedOnce {
+    const INCOMPLETE: u32 = 0;
+    const RUNNING: u32 = 1;
+    const COMPLETE: u32 = 2;
+    const POISONED: u32 = 3;
+
+    /// Creates a new instance, which can be used in statics.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(Self::INCOMPLETE),
+            waiters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns `true` if a closure has run to completion.
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == Self::COMPLETE
+    }
+
+    /// Runs `f` if no closure has been run yet, and otherwise waits until the first closure has returned, blocking the
+    /// current web worker.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance is poisoned, and on the main thread if it would have to wait, because the main thread
+    /// cannot block. Use [Self::call_once_async] there.
+    pub fn call_once(&self, f: impl FnOnce()) {
+        if self.try_run(f) {
+            return;
+        }
+        if !is_web_worker_thread() {
+            panic!("SharedOnce::call_once cannot wait on the main thread, use call_once_async instead");
+        }
+
+        let mut state = self.state.load(Ordering::Acquire);
+        while state == Self::RUNNING {
+            unsafe {
+                wasm32::memory_atomic_wait32(&self.state as *const AtomicU32 as *mut i32, Self::RUNNING as i32, -1);
+            }
+            state = self.state.load(Ordering::Acquire);
+        }
+        Self::check_poisoned(state);
+    }
+
+    /// Runs `f` if no closure has been run yet, and otherwise waits asynchronously until the first closure has
+    /// returned.
+    ///
+    /// `f` runs synchronously when the future is first polled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance is poisoned.
+    pub async fn call_once_async(&self, f: impl FnOnce()) {
+        if self.try_run(f) {
+            return;
+        }
+
+        poll_fn(|cx| {
+            // The waker is stored before checking, so that completing in between wakes it
+            self.waiters.lock_spin().unwrap().push(cx.waker().clone());
+
+            match self.state.load(Ordering::Acquire) {
+                Self::RUNNING => Poll::Pending,
+                state => {
+                    Self::check_poisoned(state);
+                    Poll::Ready(())
+                }
+            }
+        })
+        .await
+    }
+
+    // Runs `f` if no closure has been run yet. Returns `false` if another closure is still running.
+    fn try_run(&self, f: impl FnOnce()) -> bool {
+        match self
+            .state
+            .compare_exchange(Self::INCOMPLETE, Self::RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                // Poisons the instance if `f` unwinds
+                let mut finish = Finish {
+                    once: self,
+                    state: Self::POISONED,
+                };
+                f();
+                finish.state = Self::COMPLETE;
+                true
+            }
+            Err(Self::RUNNING) => false,
+            Err(state) => {
+                Self::check_poisoned(state);
+                true
+            }
+        }
+    }
+
+    fn check_poisoned(state: u32) {
+        if state == Self::POISONED {
+            panic!("SharedOnce instance has previously been poisoned");
+        }
+    }
+}
+
+impl Default for SharedOnce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SharedOnce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedOnce")
+            .field("completed", &self.is_completed())
+            .finish_non_exhaustive()
+    }
+}
+
+// Publishes the final state of a `SharedOnce` and wakes its waiters when dropped
+struct Finish<'a> {
+    once: &'a SharedOnce,
+    state: u32,
+}
+
+impl Drop for Finish<'_> {
+    fn drop(&mut self) {
+        self.once.state.store(self.state, Ordering::Release);
+
+        // Wake all blocking waiters
+        unsafe {
+            wasm32::memory_atomic_notify(&self.once.state as *const AtomicU32 as *mut i32, u32::MAX);
+        }
+
+        // Wake all async waiters
+        for waiter in self.once.waiters.lock_spin().unwrap().drain(..) {
+            waiter.wake();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::{
+    alloc::{self, Layout},
+    sync::{atomic::Ordering, Once, OnceLock},
+};
+
+use super::CURRENT_STATE;
+
+// Hook that was installed before ours, which is still called
+static PREVIOUS_HOOK: OnceLock<fn(Layout)> = OnceLock::new();
+
+// Installs an allocation error hook that marks the thread as out of memory before it aborts, so that the main thread
+// can tell the resulting trap apart from others. The hook lives in shared memory, so installing it once covers all
+// threads. A hook set by the application later replaces it.
+pub(crate) fn install_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = alloc::take_alloc_error_hook();
+        let _ = PREVIOUS_HOOK.set(previous);
+        alloc::set_alloc_error_hook(hook);
+    });
+}
+
+fn hook(layout: Layout) {
+    // Nothing here may allocate
+    CURRENT_STATE.with(|state| {
+        if let Ok(state) = state.try_borrow() {
+            if let Some(state) = &*state {
+                state.out_of_memory.store(true, Ordering::Release);
+            }
+        }
+    });
+
+    if let Some(previous) = PREVIOUS_HOOK.get() {
+        previous(layout);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{cell::RefCell, collections::VecDeque};
+
+use super::{utils, BuilderRequest, LIVE_RESOURCES};
+
+/// What happens when a thread is spawned while there are already as many live web workers as
+/// [available_parallelism](super::available_parallelism), see
+/// [Builder::oversubscription_policy](super::Builder::oversubscription_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversubscriptionPolicy {
+    /// The web worker is created regardless.
+    #[default]
+    Allow,
+    /// The web worker is created, and a warning is logged to the console.
+    Warn,
+    /// The web worker is queued until another web worker has finished.
+    Cap,
+}
+
+thread_local! {
+    // Requests held back by the `Cap` policy. Only used on the main thread, which creates all workers.
+    static CAPPED: RefCell<VecDeque<BuilderRequest>> = const { RefCell::new(VecDeque::new()) };
+}
+
+// Returns the request if its worker may be created now according to its policy, or queues it. Only call this on the
+// main thread.
+pub(crate) fn admit(request: BuilderRequest) -> Option<BuilderRequest> {
+    let policy = request.builder.oversubscription_policy;
+    if policy == OversubscriptionPolicy::Allow {
+        return Some(request);
+    }
+
+    // Without a known parallelism, nothing counts as oversubscribed
+    let parallelism = match utils::available_parallelism() {
+        Ok(parallelism) => parallelism.get(),
+        Err(_) => return Some(request),
+    };
+    let live = LIVE_RESOURCES.with(|live| live.borrow().len());
+    if live < parallelism {
+        return Some(request);
+    }
+
+    match policy {
+        OversubscriptionPolicy::Cap => {
+            CAPPED.with(|capped| capped.borrow_mut().push_back(request));
+            None
+        }
+        _ => {
+            utils::console_warn(&format!(
+                "wasm_thread: spawning a web worker with {} already live exceeds the available parallelism of {}",
+                live, parallelism
+            ));
+            Some(request)
+        }
+    }
+}
+
+// Spawns the oldest capped request, if any, after a web worker has finished. Only call this on the main thread.
+pub(crate) unsafe fn release() {
+    // Spawn outside of the borrow, because spawning may queue the request again
+    while let Some(request) = CAPPED.with(|capped| capped.borrow_mut().pop_front()) {
+        // Threads aborted while queued never get a worker, so they do not take the free slot
+        if request.context.state.termination().is_none() {
+            request.spawn();
+            break;
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    panic::{self, catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::Once,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+
+use super::{Builder, JoinHandle};
+
+/// Description of a panic delivered to a [PanicStream], see [Builder::panic_channel].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicReport {
+    message: String,
+    location: Option<String>,
+}
+
+impl PanicReport {
+    /// Returns the panic message, or a placeholder if the payload was neither a `&str` nor a `String`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the source location of the panic as `file:line:column`, if known.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} at {}", self.message, location),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Stream of the panics of a thread spawned with [Builder::panic_channel].
+///
+/// The stream ends once the thread has completed. It never ends if the thread is terminated or traps, as the thread
+/// cannot release its end of the channel then.
+#[derive(Debug)]
+pub struct PanicStream {
+    receiver: UnboundedReceiver<PanicReport>,
+}
+
+impl Stream for PanicStream {
+    type Item = PanicReport;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<PanicReport>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+thread_local! {
+    // Channel of the thread running in this web worker, if it was spawned with `Builder::panic_channel`
+    static PANIC_SENDER: RefCell<Option<UnboundedSender<PanicReport>>> = const { RefCell::new(None) };
+}
+
+// The panic hook is global to all threads sharing the memory, so it is installed once and reports only panics of
+// threads with a channel. Other panics, and the reported ones too, are passed on to the previous hook.
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            PANIC_SENDER.with(|sender| {
+                if let Some(sender) = &*sender.borrow() {
+                    let payload = info.payload();
+                    let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+                        (Some(message), _) => message.to_string(),
+                        (_, Some(message)) => message.clone(),
+                        _ => "Box<dyn Any>".to_string(),
+                    };
+                    let location = info.location().map(|location| location.to_string());
+                    // The stream may have been dropped, in which case nobody is interested anymore
+                    let _ = sender.unbounded_send(PanicReport { message, location });
+                }
+            });
+            previous(info);
+        }));
+    });
+}
+
+impl Builder {
+    /// Spawns a new thread whose panics are delivered to the returned [PanicStream] instead of its [`JoinHandle`].
+    ///
+    /// Joining yields `Some` with the result of `f` if the thread completes, and `None` if it panicked, so that the
+    /// result type stays free of error handling. The panic message and location are delivered to the stream before
+    /// the join handle resolves.
+    ///
+    /// With `panic=abort`, a panicking thread traps instead of unwinding. The panic is still delivered to the stream,
+    /// but joining fails with [Termination::Trapped](super::Termination::Trapped).
+    pub fn panic_channel<F, T>(self, f: F) -> std::io::Result<(PanicStream, JoinHandle<Option<T>>)>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        install_hook();
+
+        let (sender, receiver) = mpsc::unbounded();
+        let handle = self.spawn(move || {
+            PANIC_SENDER.with(|slot| *slot.borrow_mut() = Some(sender));
+            let result = catch_unwind(AssertUnwindSafe(f)).ok();
+            // Ends the stream
+            PANIC_SENDER.with(|slot| slot.borrow_mut().take());
+            result
+        })?;
+
+        Ok((PanicStream { receiver }, handle))
+    }
+}
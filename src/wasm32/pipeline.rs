@@ -0,0 +1,155 @@
+use std::pin::pin;
+
+use futures::{
+    channel::mpsc::{self, Receiver},
+    executor::{self, BlockingStream},
+    future::{self, Either},
+    stream::{FuturesUnordered, StreamExt},
+    SinkExt,
+};
+
+use super::{Builder, JoinHandle, Result};
+
+/// Chain of threads where each stage consumes the items produced by the previous one.
+///
+/// Every stage runs in a thread of its own, and consecutive stages are connected by a queue in shared memory, so items
+/// flow from stage to stage without involving the main thread. Only the output of the last stage is delivered to the
+/// spawning thread, by [Self::collect].
+///
+/// # Backpressure
+///
+/// Each queue buffers up to `capacity` items plus one item in flight. A stage that finds its output queue full blocks
+/// until the next stage has taken an item, so a slow stage throttles all stages before it instead of letting the
+/// queues grow. Items that are not collected keep the last stage blocked.
+#[derive(Debug)]
+pub struct Pipeline<T> {
+    builder: Builder,
+    capacity: usize,
+    output: Receiver<T>,
+    stages: Vec<JoinHandle<()>>,
+}
+
+/// Items produced by the previous stage of a [Pipeline].
+///
+/// Iterating blocks until the previous stage has produced the next item, and ends once it has completed.
+#[derive(Debug)]
+pub struct StageInput<T>(BlockingStream<Receiver<T>>);
+
+impl<T> Iterator for StageInput<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T: Send + 'static> Pipeline<T> {
+    /// Creates a pipeline whose first stage yields the items of the iterator returned by `source`, in a thread spawned
+    /// by [Builder::new]. The queues between stages buffer up to `capacity` items.
+    pub fn new<F, I>(capacity: usize, source: F) -> std::io::Result<Pipeline<T>>
+    where
+        F: FnOnce() -> I,
+        F: Send + 'static,
+        I: IntoIterator<Item = T>,
+    {
+        Self::with_builder(Builder::new(), capacity, source)
+    }
+
+    /// Creates a pipeline whose first stage yields the items of the iterator returned by `source`. All stages are
+    /// spawned by clones of `builder`, and the queues between them buffer up to `capacity` items.
+    pub fn with_builder<F, I>(builder: Builder, capacity: usize, source: F) -> std::io::Result<Pipeline<T>>
+    where
+        F: FnOnce() -> I,
+        F: Send + 'static,
+        I: IntoIterator<Item = T>,
+    {
+        let (output, stage) = spawn_stage(builder.clone(), capacity, source)?;
+        Ok(Pipeline {
+            builder,
+            capacity,
+            output,
+            stages: vec![stage],
+        })
+    }
+
+    /// Appends a stage that yields the items of the iterator returned by `f`, which receives the items of the previous
+    /// stage.
+    ///
+    /// The stage is spawned right away and starts consuming items as soon as they are produced. If spawning fails,
+    /// the pipeline is dropped and its stages stop once their queues are full.
+    pub fn stage<U, F, I>(self, f: F) -> std::io::Result<Pipeline<U>>
+    where
+        F: FnOnce(StageInput<T>) -> I,
+        F: Send + 'static,
+        I: IntoIterator<Item = U>,
+        U: Send + 'static,
+    {
+        let Pipeline {
+            builder,
+            capacity,
+            output,
+            mut stages,
+        } = self;
+
+        let input = StageInput(executor::block_on_stream(output));
+        let (output, stage) = spawn_stage(builder.clone(), capacity, move || f(input))?;
+        stages.push(stage);
+        Ok(Pipeline {
+            builder,
+            capacity,
+            output,
+            stages,
+        })
+    }
+
+    /// Collects the items of the last stage once all stages have completed.
+    ///
+    /// Fails with the error of the first stage that fails. Stages after a stage that is terminated or traps never see
+    /// the end of their input, so they keep running detached, while the stages before it stop once their queues are
+    /// full.
+    pub async fn collect(self) -> Result<Vec<T>> {
+        let mut stages: FuturesUnordered<_> = self.stages.into_iter().map(JoinHandle::join_async).collect();
+
+        let items = {
+            let items = pin!(self.output.collect::<Vec<T>>());
+            let failure = pin!(async {
+                while let Some(result) = stages.next().await {
+                    result?;
+                }
+                // All stages have completed, so the items are about to be collected
+                future::pending::<Result<()>>().await
+            });
+            match future::select(items, failure).await {
+                Either::Left((items, _)) => items,
+                Either::Right((Err(payload), _)) => return Err(payload),
+                Either::Right((Ok(()), _)) => unreachable!(),
+            }
+        };
+
+        // The last stage may still be shutting down, and earlier stages may have failed after producing their items
+        while let Some(result) = stages.next().await {
+            result?;
+        }
+        Ok(items)
+    }
+}
+
+// Spawns a stage that sends the items returned by `produce` to a new queue, and returns the receiving end of the queue
+fn spawn_stage<F, I, T>(builder: Builder, capacity: usize, produce: F) -> std::io::Result<(Receiver<T>, JoinHandle<()>)>
+where
+    F: FnOnce() -> I,
+    F: Send + 'static,
+    I: IntoIterator<Item = T>,
+    T: Send + 'static,
+{
+    let (mut sender, receiver) = mpsc::channel(capacity);
+    let stage = builder.spawn(move || {
+        for item in produce() {
+            // The next stage has stopped, so nobody is interested in more items
+            if executor::block_on(sender.send(item)).is_err() {
+                break;
+            }
+        }
+    })?;
+    Ok((receiver, stage))
+}
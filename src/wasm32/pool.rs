@@ -0,0 +1,226 @@
+use std::{
+    cell::RefCell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::poll_fn,
+    Stream, StreamExt,
+};
+use web_sys::Worker;
+
+use super::{utils::SpinLockMutex, ThreadState, WorkerCredentials, WorkerId, WorkerKind};
+
+// Settings a web worker is created with, which jobs must share to run on the same worker
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PoolKey {
+    pub(crate) script: String,
+    pub(crate) worker_type: Option<WorkerKind>,
+    pub(crate) credentials: Option<WorkerCredentials>,
+}
+
+// A pooled worker waiting for its next job
+struct IdleWorker {
+    key: PoolKey,
+    id: WorkerId,
+    // Affinity of the last thread that ran on the worker, if it had one
+    affinity: Option<u64>,
+    worker: Rc<Worker>,
+}
+
+thread_local! {
+    // Idle pooled workers, waiting for their next job. Only used on the main thread.
+    static IDLE_WORKERS: RefCell<Vec<IdleWorker>> = const { RefCell::new(Vec::new()) };
+    // Wakes `drain_worker_pool` once a pooled thread has been settled. Only used on the main thread.
+    static DRAIN_WAKER: RefCell<Option<Waker>> = const { RefCell::new(None) };
+    // Subscribers to `idle_events`. Only used on the main thread.
+    static IDLE_SUBSCRIBERS: RefCell<Vec<UnboundedSender<WorkerId>>> = const { RefCell::new(Vec::new()) };
+}
+
+// Pooled threads whose completion or termination has not been handled by the main thread yet, including queued ones.
+// Pooled threads are spawned from any thread, so this lives in shared memory.
+static PENDING: Mutex<Vec<Arc<ThreadState>>> = Mutex::new(Vec::new());
+
+// Set while `drain_worker_pool` is running
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+// Fails while the pool is being drained, instead of spawning a pooled thread
+pub(crate) fn check_not_draining() -> io::Result<()> {
+    if DRAINING.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Other, "the worker pool is draining"));
+    }
+    Ok(())
+}
+
+// Tracks a pooled thread until it has been settled, so that draining waits for it
+pub(crate) fn track(state: &Arc<ThreadState>) {
+    PENDING.lock_spin().unwrap().push(state.clone());
+}
+
+// Stops tracking a thread once the main thread has returned its worker to the pool or terminated it, or the thread
+// was terminated before its worker was created. Does nothing for threads that are not pooled.
+pub(crate) fn settle(state: &Arc<ThreadState>) {
+    {
+        let mut pending = PENDING.lock_spin().unwrap();
+        match pending.iter().position(|pending| Arc::ptr_eq(pending, state)) {
+            Some(index) => pending.swap_remove(index),
+            None => return,
+        };
+    }
+
+    if let Some(waker) = DRAIN_WAKER.with(|waker| waker.borrow_mut().take()) {
+        waker.wake();
+    }
+}
+
+// Takes an idle worker created with the settings of `key`, if there is one. The worker that last ran a thread with
+// the same `affinity` is preferred, and otherwise one that is not preferred by any other threads.
+pub(crate) fn take(key: &PoolKey, affinity: Option<u64>) -> Option<(WorkerId, Rc<Worker>)> {
+    IDLE_WORKERS.with(|idle| {
+        let mut idle = idle.borrow_mut();
+        let candidates = || idle.iter().enumerate().filter(|(_, idle)| idle.key == *key);
+        let (index, _) = candidates()
+            .find(|(_, idle)| affinity.is_some() && idle.affinity == affinity)
+            .or_else(|| candidates().find(|(_, idle)| idle.affinity.is_none()))
+            .or_else(|| candidates().next())?;
+        let idle = idle.swap_remove(index);
+        Some((idle.id, idle.worker))
+    })
+}
+
+// Keeps a worker that completed a thread with `affinity` for the next job, unless `capacity` workers with the same
+// settings are idle already, in which case it is terminated
+pub(crate) fn recycle(key: PoolKey, affinity: Option<u64>, capacity: usize, id: WorkerId, worker: Rc<Worker>) {
+    let kept = IDLE_WORKERS.with(|idle| {
+        let mut idle = idle.borrow_mut();
+        let draining = DRAINING.load(Ordering::Relaxed);
+        if !draining && idle.iter().filter(|idle| idle.key == key).count() < capacity {
+            idle.push(IdleWorker {
+                key,
+                id,
+                affinity,
+                worker,
+            });
+            true
+        } else {
+            worker.terminate();
+            false
+        }
+    });
+
+    // Notified outside of the borrow, in case a subscriber spawns a thread right away
+    if kept {
+        IDLE_SUBSCRIBERS.with(|subscribers| {
+            subscribers
+                .borrow_mut()
+                .retain(|subscriber| subscriber.unbounded_send(id).is_ok())
+        });
+    }
+}
+
+/// Stream of the web workers of the [worker pool](super::Builder::pool_size) that have become idle, see [idle_events].
+#[derive(Debug)]
+pub struct IdleEvents {
+    receiver: UnboundedReceiver<WorkerId>,
+}
+
+impl Stream for IdleEvents {
+    type Item = WorkerId;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WorkerId>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+/// Returns a stream of the identifiers of pooled web workers as they become idle.
+///
+/// A worker becomes idle once its thread has completed and the worker has been returned to the
+/// [pool](super::Builder::pool_size), so the next pooled thread with the same settings runs on it right away. This
+/// lets an application feed work from its own queue exactly when capacity frees up. Workers that are terminated
+/// instead, e.g. because the pool is full or its thread trapped, are not reported. A pooled worker keeps its
+/// identifier across threads. Each call returns a separate stream receiving all later events, and the stream never
+/// ends.
+///
+/// # Panics
+///
+/// Panics in a web worker, because the pool is owned by the main thread.
+pub fn idle_events() -> IdleEvents {
+    if super::is_web_worker_thread() {
+        panic!("idle events can only be received on the main thread");
+    }
+
+    let (sender, receiver) = mpsc::unbounded();
+    IDLE_SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push(sender));
+    IdleEvents { receiver }
+}
+
+/// Terminates the idle web workers kept for reuse by [Builder::pool_size](super::Builder::pool_size).
+///
+/// Workers that are running a thread are unaffected, and are returned to the pool once the thread completes. Idle
+/// workers are also terminated by [terminate_all_workers](super::terminate_all_workers) and, on the main thread, by
+/// [shutdown](super::shutdown).
+///
+/// # Panics
+///
+/// Panics in a web worker, because the pool is owned by the main thread.
+pub fn clear_worker_pool() {
+    if super::is_web_worker_thread() {
+        panic!("the worker pool can only be cleared on the main thread");
+    }
+
+    drop_idle_workers();
+}
+
+/// Waits for all pooled threads to finish, and then terminates the idle workers of the pool.
+///
+/// Unlike [clear_worker_pool], which only terminates the workers that are idle right away, this lets pooled threads
+/// that are queued, e.g. by [set_spawn_rate_limit](super::set_spawn_rate_limit), or running complete first. Unlike
+/// [terminate_all_workers](super::terminate_all_workers), no thread is terminated. Spawning pooled threads fails
+/// until the returned future resolves, and the pool can be used again afterwards.
+///
+/// # Panics
+///
+/// Panics in a web worker, because the pool is owned by the main thread.
+pub async fn drain_worker_pool() {
+    if super::is_web_worker_thread() {
+        panic!("the worker pool can only be drained on the main thread");
+    }
+
+    DRAINING.store(true, Ordering::Relaxed);
+    // Threads are settled on the main thread, where this future is polled, so none can be settled in between checking
+    // and storing the waker. Workers are returned to the pool before their thread is settled, so none can be returned
+    // once all are.
+    poll_fn(|cx| {
+        if PENDING.lock_spin().unwrap().is_empty() {
+            return Poll::Ready(());
+        }
+        DRAIN_WAKER.with(|waker| *waker.borrow_mut() = Some(cx.waker().clone()));
+        Poll::Pending
+    })
+    .await;
+    drop_idle_workers();
+    DRAINING.store(false, Ordering::Relaxed);
+}
+
+// Terminates the idle workers of the calling thread, which only has any on the main thread
+pub(crate) fn drop_idle_workers() {
+    let idle = IDLE_WORKERS.with(|idle| std::mem::take(&mut *idle.borrow_mut()));
+    for idle in idle {
+        idle.worker.terminate();
+    }
+}
+
+/// Returns the number of idle web workers kept for reuse by [Builder::pool_size](super::Builder::pool_size).
+///
+/// This is always 0 in web workers, because the pool is owned by the main thread.
+pub fn idle_worker_count() -> usize {
+    IDLE_WORKERS.with(|idle| idle.borrow().len())
+}
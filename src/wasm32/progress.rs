@@ -0,0 +1,100 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+
+/// Reports the progress of a thread to a [ProgressStream], throttling updates.
+///
+/// Progress is a fraction between `0.0` and `1.0`. Updates are sent at most once per interval (10 times a second by
+/// default) and only if they advance the progress, so the stream observes a monotonic subset of the reported values.
+/// Completion (`1.0`) is always delivered immediately, and the latest pending update is flushed when the reporter is
+/// dropped.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    sender: UnboundedSender<f32>,
+    interval: f64,
+    // Time of the last sent update in milliseconds
+    last_sent: Option<f64>,
+    // Highest progress reported so far
+    latest: f32,
+    // Whether `latest` has been reported but not sent yet
+    pending: bool,
+}
+
+impl ProgressReporter {
+    /// Creates a reporter that sends at most 10 updates a second, together with the stream receiving them.
+    pub fn new() -> (ProgressReporter, ProgressStream) {
+        Self::with_interval(Duration::from_millis(100))
+    }
+
+    /// Creates a reporter that sends at most one update per `interval`, together with the stream receiving them.
+    pub fn with_interval(interval: Duration) -> (ProgressReporter, ProgressStream) {
+        let (sender, receiver) = mpsc::unbounded();
+        let reporter = ProgressReporter {
+            sender,
+            interval: interval.as_secs_f64() * 1000.0,
+            last_sent: None,
+            latest: 0.0,
+            pending: false,
+        };
+        (reporter, ProgressStream { receiver })
+    }
+
+    /// Reports the current progress as a fraction between `0.0` and `1.0`.
+    ///
+    /// Values outside of that range are clamped, and values that do not advance the progress are ignored.
+    pub fn report(&mut self, fraction: f32) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        if fraction <= self.latest && self.last_sent.is_some() {
+            return;
+        }
+        self.latest = fraction;
+        self.pending = true;
+
+        let now = js_sys::Date::now();
+        let due = match self.last_sent {
+            Some(last_sent) => now - last_sent >= self.interval,
+            None => true,
+        };
+        if due || fraction >= 1.0 {
+            self.flush(now);
+        }
+    }
+
+    fn flush(&mut self, now: f64) {
+        if self.pending {
+            // The stream may have been dropped, in which case nobody is interested anymore
+            let _ = self.sender.unbounded_send(self.latest);
+            self.last_sent = Some(now);
+            self.pending = false;
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.flush(js_sys::Date::now());
+    }
+}
+
+/// Stream of throttled progress updates sent by a [ProgressReporter].
+///
+/// The stream ends once the reporter is dropped.
+#[derive(Debug)]
+pub struct ProgressStream {
+    receiver: UnboundedReceiver<f32>,
+}
+
+impl Stream for ProgressStream {
+    type Item = f32;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<f32>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
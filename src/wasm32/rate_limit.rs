@@ -0,0 +1,112 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use wasm_bindgen::prelude::*;
+
+use super::{utils, BuilderRequest};
+
+// Maximum number of workers created per second, or 0 for no limit
+static SPAWN_RATE_LIMIT: AtomicU32 = AtomicU32::new(0);
+
+/// Limits how many web workers are created per second, or removes the limit with `None`.
+///
+/// Spawning still returns immediately, but the web workers of threads spawned beyond the rate are queued and created
+/// over time, at least `1 / per_second` seconds apart. This smooths out bursts of spawns that could otherwise
+/// overwhelm the browser. Removing the limit releases all queued workers at once.
+pub fn set_spawn_rate_limit(per_second: Option<NonZeroU32>) {
+    SPAWN_RATE_LIMIT.store(per_second.map_or(0, NonZeroU32::get), Ordering::Relaxed);
+}
+
+// Interval between worker creations in milliseconds, if limited
+fn interval() -> Option<f64> {
+    match SPAWN_RATE_LIMIT.load(Ordering::Relaxed) {
+        0 => None,
+        per_second => Some(1000.0 / per_second as f64),
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    requests: VecDeque<BuilderRequest>,
+    // Time in milliseconds at which the next worker may be created
+    next_release: f64,
+    // Whether a timer to release queued requests is pending
+    armed: bool,
+}
+
+thread_local! {
+    // Only used on the main thread, which creates all workers
+    static QUEUE: RefCell<Queue> = RefCell::new(Queue::default());
+}
+
+// Creates the worker for `request` now, or queues it if the rate limit has been reached. Only call this on the main
+// thread, with the same guarantees as for spawning the request directly.
+pub(crate) unsafe fn schedule(request: BuilderRequest) {
+    let interval = match interval() {
+        Some(interval) => interval,
+        None => return request.spawn(),
+    };
+
+    let now = utils::performance_now();
+    let request = QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        if queue.requests.is_empty() && now >= queue.next_release {
+            queue.next_release = now + interval;
+            Some(request)
+        } else {
+            queue.requests.push_back(request);
+            if !queue.armed {
+                queue.armed = true;
+                arm(queue.next_release - now);
+            }
+            None
+        }
+    });
+
+    // Spawn outside of the borrow, because spawning may reenter the queue
+    if let Some(request) = request {
+        request.spawn();
+    }
+}
+
+fn arm(delay: f64) {
+    let release = Closure::once_into_js(|| unsafe { release() });
+    utils::set_timeout(release.unchecked_ref(), delay.ceil().max(0.0) as i32);
+}
+
+// Releases queued requests that are due and re-arms the timer for the rest
+unsafe fn release() {
+    loop {
+        let now = utils::performance_now();
+        let request = QUEUE.with(|queue| {
+            let mut queue = queue.borrow_mut();
+            match interval() {
+                Some(_) if now < queue.next_release => {
+                    if !queue.requests.is_empty() {
+                        arm(queue.next_release - now);
+                    } else {
+                        queue.armed = false;
+                    }
+                    None
+                }
+                interval => {
+                    let request = queue.requests.pop_front();
+                    match request {
+                        Some(_) => queue.next_release = now + interval.unwrap_or(0.0),
+                        None => queue.armed = false,
+                    }
+                    request
+                }
+            }
+        });
+
+        match request {
+            Some(request) => request.spawn(),
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use super::{utils::SpinLockMutex, ThreadId, ThreadState};
+
+/// A unique identifier for a web worker spawned by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WorkerId(u64);
+
+impl WorkerId {
+    pub(crate) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the numeric value of this identifier.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Information about a live web worker spawned by this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerInfo {
+    id: WorkerId,
+    name: Option<String>,
+    tag: Option<u64>,
+}
+
+impl WorkerInfo {
+    pub(crate) fn new(id: WorkerId, name: Option<String>, tag: Option<u64>) -> Self {
+        Self { id, name, tag }
+    }
+
+    /// Returns the unique identifier of the worker.
+    pub fn id(&self) -> WorkerId {
+        self.id
+    }
+
+    /// Returns the name the worker was spawned with, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the tag set by [Builder::tag](super::Builder::tag), if any.
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+}
+
+/// A serializable reference to a thread spawned by this crate, see [JoinHandle::token](super::JoinHandle::token).
+///
+/// Unlike the join handle, a token can be copied freely and passed between independent parts of an application, e.g.
+/// through a message or as JSON with the `serde` feature, which then wait for the thread with [join_by_token]. It is
+/// only meaningful within the instance of the wasm module that spawned the thread, including its web workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkerToken(u64);
+
+impl WorkerToken {
+    pub(crate) fn new(id: ThreadId) -> Self {
+        Self(id.as_u64().get())
+    }
+
+    /// Returns the numeric value of this token.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Recreates a token from the value returned by [Self::as_u64].
+    pub fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+// States of the threads that can be joined by token, which are kept alive by their join handle or web worker
+static TOKENS: Mutex<BTreeMap<WorkerToken, Weak<ThreadState>>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn register_token(token: WorkerToken, state: &Arc<ThreadState>) {
+    let mut tokens = TOKENS.lock_spin().unwrap();
+    tokens.retain(|_, state| state.strong_count() > 0);
+    tokens.insert(token, Arc::downgrade(state));
+}
+
+/// Waits for the thread referred to by `token` to finish, without taking its result.
+///
+/// Returns `Ok(())` once the thread has run to completion, including right away if it has completed already. Whether
+/// it panicked is only reported by its [JoinHandle](super::JoinHandle). If the thread was terminated, the error wraps
+/// the [Termination](super::Termination) reason. Once a thread has finished and its join handle has been dropped or
+/// joined, it is forgotten, and the error is of kind [io::ErrorKind::NotFound]. Tokens that were never handed out are
+/// rejected with an error of kind [io::ErrorKind::InvalidInput].
+pub async fn join_by_token(token: WorkerToken) -> io::Result<()> {
+    let state = TOKENS.lock_spin().unwrap().get(&token).and_then(Weak::upgrade);
+    let state = match state {
+        Some(state) => state,
+        None if ThreadId::was_issued(token.0) => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the thread has finished and its join handle is gone",
+            ))
+        }
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown worker token")),
+    };
+
+    // Interrupting a join on the handle does not finish the thread
+    state.signal.wait_signaled_async().await;
+    match state.termination() {
+        Some(termination) => Err(io::Error::new(io::ErrorKind::Other, termination)),
+        None => Ok(()),
+    }
+}
+
+// Workers that have been created and have not completed yet. This lives in shared memory so that it can be queried
+// from any thread, even though only the main thread creates and retires workers.
+static LIVE_WORKERS: Mutex<BTreeMap<WorkerId, WorkerInfo>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn register(info: WorkerInfo) {
+    LIVE_WORKERS.lock_spin().unwrap().insert(info.id, info);
+}
+
+pub(crate) fn unregister(id: WorkerId) {
+    LIVE_WORKERS.lock_spin().unwrap().remove(&id);
+}
+
+/// Returns all live workers that were spawned with the given [tag](super::Builder::tag).
+///
+/// A worker is live from the moment the main thread creates it until it completes or is terminated. Threads spawned
+/// from within a web worker are created asynchronously by the main thread, so they may not show up immediately.
+pub fn find_workers_by_tag(tag: u64) -> Vec<WorkerInfo> {
+    LIVE_WORKERS
+        .lock_spin()
+        .unwrap()
+        .values()
+        .filter(|info| info.tag == Some(tag))
+        .cloned()
+        .collect()
+}
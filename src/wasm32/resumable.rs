@@ -0,0 +1,73 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future, task::noop_waker_ref};
+
+use super::{Builder, JoinHandle};
+
+/// Future of a thread spawned with [Builder::spawn_resumable], suspended at its first suspension point.
+///
+/// Awaiting it resumes the future on the awaiting thread. If the future completed on the thread already, its output is
+/// returned right away.
+pub struct Suspended<T> {
+    future: Pin<Box<dyn Future<Output = T> + Send>>,
+}
+
+impl<T> Future for Suspended<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+impl<T> fmt::Debug for Suspended<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Suspended").finish_non_exhaustive()
+    }
+}
+
+impl Builder {
+    /// Spawns a new thread that polls the future returned by `f` until its first suspension point, and returns an
+    /// [std::io::Result] to a [`JoinHandle`] yielding the [Suspended] future, which can be resumed on another thread.
+    ///
+    /// The future is boxed before it is polled, so it stays pinned in shared memory while it is handed over. The
+    /// thread polls it with a waker that does nothing, and the thread resuming it registers its own waker with the next
+    /// poll.
+    ///
+    /// The future must be `Send`: a future holding thread-local state across a suspension point, e.g. a `JsValue` or
+    /// an `Rc`, would access that state from the wrong thread once resumed, because JS values and thread locals are not
+    /// shared between web workers even though the memory is. Such futures are rejected at compile time:
+    ///
+    /// ```compile_fail
+    /// let handle = wasm_thread::Builder::new().spawn_resumable(|| async {
+    ///     let local = std::rc::Rc::new(1);
+    ///     futures::future::pending::<()>().await;
+    ///     *local
+    /// });
+    /// ```
+    ///
+    /// Keep such state inside the thread, and hand over only `Send` values, or spawn the whole future with
+    /// [Self::spawn_owning] instead.
+    pub fn spawn_resumable<F, Fut>(self, f: F) -> std::io::Result<JoinHandle<Suspended<Fut::Output>>>
+    where
+        F: FnOnce() -> Fut,
+        F: Send + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.spawn(move || {
+            let mut future = Box::pin(f());
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let future: Pin<Box<dyn Future<Output = Fut::Output> + Send>> = match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => Box::pin(future::ready(output)),
+                Poll::Pending => future,
+            };
+            Suspended { future }
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use std::marker::PhantomData;
+
+use super::{Builder, Result};
+
+/// A task that can be run again in a fresh thread if it fails, see [Builder::spawn_retryable].
+#[derive(Debug)]
+pub struct RetryableHandle<M, T> {
+    builder: Builder,
+    make_f: M,
+    max_retries: usize,
+    retries: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<M, F, T> RetryableHandle<M, T>
+where
+    M: Fn() -> F,
+    F: FnOnce() -> T,
+    F: Send + 'static,
+    T: Send + 'static,
+{
+    pub(crate) fn new(builder: Builder, make_f: M) -> Self {
+        Self {
+            builder,
+            make_f,
+            max_retries: 3,
+            retries: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets how many times [Self::retry] may run the task again after [Self::run]. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Returns how many retries have been run since the last call to [Self::run].
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Runs the task in a new thread and waits for it to finish, resetting the retry count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread could not be spawned, like [spawn](super::spawn).
+    pub async fn run(&mut self) -> Result<T> {
+        self.retries = 0;
+        self.attempt().await
+    }
+
+    /// Runs the task again in a new thread and waits for it to finish, or returns `None` once the task has been
+    /// retried [Self::max_retries] times.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread could not be spawned, like [spawn](super::spawn).
+    pub async fn retry(&mut self) -> Option<Result<T>> {
+        if self.retries >= self.max_retries {
+            return None;
+        }
+        self.retries += 1;
+        Some(self.attempt().await)
+    }
+
+    async fn attempt(&self) -> Result<T> {
+        self.builder
+            .clone()
+            .spawn((self.make_f)())
+            .expect("failed to spawn thread")
+            .join_async()
+            .await
+    }
+}
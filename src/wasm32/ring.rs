@@ -0,0 +1,227 @@
+use std::{
+    arch::wasm32,
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::Stream;
+
+use super::utils::{is_web_worker_thread, SpinLockMutex};
+
+// Ring buffer in shared memory. The cursors count the samples written and read so far modulo twice the capacity, so
+// that a full buffer can be told apart from an empty one, and index the buffer modulo its capacity. Letting them wrap
+// around at `u32::MAX` instead would make slots overlap after the wrap, unless the capacity divides 2^32.
+struct Ring {
+    samples: Box<[AtomicU32]>,
+    written: AtomicU32,
+    read: AtomicU32,
+    // Set once the sink has been dropped
+    closed: AtomicBool,
+    // Set once the stream has been dropped
+    abandoned: AtomicBool,
+    reader: Mutex<Option<Waker>>,
+}
+
+impl fmt::Debug for Ring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ring")
+            .field("capacity", &self.capacity())
+            .field("written", &self.written)
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Ring {
+    fn capacity(&self) -> u32 {
+        self.samples.len() as u32
+    }
+
+    // Index of the slot at `cursor`
+    fn slot(&self, cursor: u32) -> usize {
+        (cursor % self.capacity()) as usize
+    }
+
+    // Cursor following `cursor`. Twice the capacity fits into `u32`, as the capacity is at most `i32::MAX`.
+    fn advance(&self, cursor: u32) -> u32 {
+        (cursor + 1) % (2 * self.capacity())
+    }
+
+    // Number of samples written but not yet read
+    fn len(&self, read: u32, written: u32) -> u32 {
+        if written >= read {
+            written - read
+        } else {
+            written + (2 * self.capacity() - read)
+        }
+    }
+
+    fn wake_reader(&self) {
+        if let Some(waker) = self.reader.lock_spin().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_writer(&self) {
+        unsafe {
+            wasm32::memory_atomic_notify(&self.read as *const AtomicU32 as *mut i32, 1);
+        }
+    }
+}
+
+/// Writes samples into a ring buffer in shared memory, which the main thread reads through a [FloatRingStream].
+///
+/// Samples are stored in the shared wasm memory, a `SharedArrayBuffer`, and only their cursors are synchronized, so
+/// streaming them costs no message per sample or chunk. This suits numeric output, e.g. of DSP or ML workloads. The
+/// stream ends once the sink is dropped and all samples have been read.
+#[derive(Debug)]
+pub struct FloatRingSink {
+    ring: Arc<Ring>,
+}
+
+impl FloatRingSink {
+    /// Creates a sink and the stream reading from it, sharing a ring buffer of `capacity` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero or larger than `i32::MAX`.
+    pub fn new(capacity: usize) -> (FloatRingSink, FloatRingStream) {
+        Self::with_cursor(capacity, 0)
+    }
+
+    // Creates a sink and stream with both cursors starting at `cursor`, so that tests can reach the point where the
+    // cursors wrap around without writing billions of samples first
+    #[doc(hidden)]
+    pub fn with_cursor(capacity: usize, cursor: u32) -> (FloatRingSink, FloatRingStream) {
+        assert!(
+            capacity > 0 && capacity <= i32::MAX as usize,
+            "ring buffer capacity must be between 1 and `i32::MAX`"
+        );
+
+        let cursor = cursor % (2 * capacity as u32);
+        let ring = Arc::new(Ring {
+            samples: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicU32::new(cursor),
+            read: AtomicU32::new(cursor),
+            closed: AtomicBool::new(false),
+            abandoned: AtomicBool::new(false),
+            reader: Mutex::new(None),
+        });
+        (FloatRingSink { ring: ring.clone() }, FloatRingStream { ring })
+    }
+
+    /// Writes `samples` into the ring buffer, blocking while it is full.
+    ///
+    /// Returns `false` without writing the remaining samples if the stream has been dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, which cannot block, if the buffer is full.
+    pub fn write(&mut self, mut samples: &[f32]) -> bool {
+        let ring = &*self.ring;
+        let capacity = ring.capacity();
+        let mut cursor = ring.written.load(Ordering::Relaxed);
+        while !samples.is_empty() {
+            if ring.abandoned.load(Ordering::Acquire) {
+                return false;
+            }
+
+            let read = ring.read.load(Ordering::Acquire);
+            let free = capacity - ring.len(read, cursor);
+            if free == 0 {
+                if !is_web_worker_thread() {
+                    panic!("`FloatRingSink::write` cannot block on the main thread while the ring buffer is full");
+                }
+                unsafe {
+                    wasm32::memory_atomic_wait32(&ring.read as *const AtomicU32 as *mut i32, read as i32, -1);
+                }
+                continue;
+            }
+
+            let (chunk, rest) = samples.split_at(samples.len().min(free as usize));
+            for sample in chunk {
+                ring.samples[ring.slot(cursor)].store(sample.to_bits(), Ordering::Relaxed);
+                cursor = ring.advance(cursor);
+            }
+            ring.written.store(cursor, Ordering::Release);
+            ring.wake_reader();
+            samples = rest;
+        }
+        true
+    }
+}
+
+impl Drop for FloatRingSink {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+        self.ring.wake_reader();
+    }
+}
+
+/// Stream of the samples written by a [FloatRingSink], in chunks of all samples available when it is polled.
+#[derive(Debug)]
+pub struct FloatRingStream {
+    ring: Arc<Ring>,
+}
+
+impl FloatRingStream {
+    // Takes all available samples, if any
+    fn take(&self) -> Option<Vec<f32>> {
+        let ring = &*self.ring;
+        let read = ring.read.load(Ordering::Relaxed);
+        let written = ring.written.load(Ordering::Acquire);
+        if written == read {
+            return None;
+        }
+
+        let mut cursor = read;
+        let mut chunk = Vec::with_capacity(ring.len(read, written) as usize);
+        while cursor != written {
+            chunk.push(f32::from_bits(ring.samples[ring.slot(cursor)].load(Ordering::Relaxed)));
+            cursor = ring.advance(cursor);
+        }
+        ring.read.store(written, Ordering::Release);
+        ring.wake_writer();
+        Some(chunk)
+    }
+}
+
+impl Stream for FloatRingStream {
+    type Item = Vec<f32>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<f32>>> {
+        // Checked before taking, so that samples written before closing are not missed
+        let closed = self.ring.closed.load(Ordering::Acquire);
+        if let Some(chunk) = self.take() {
+            return Poll::Ready(Some(chunk));
+        }
+        if closed {
+            return Poll::Ready(None);
+        }
+
+        // The waker is stored before checking again, so that a write in between wakes it
+        *self.ring.reader.lock_spin().unwrap() = Some(cx.waker().clone());
+        let closed = self.ring.closed.load(Ordering::Acquire);
+        match self.take() {
+            Some(chunk) => Poll::Ready(Some(chunk)),
+            None if closed => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for FloatRingStream {
+    fn drop(&mut self) {
+        self.ring.abandoned.store(true, Ordering::Release);
+        // Discarding the unread samples changes the read cursor, so that a writer about to wait for it does not block
+        self.ring
+            .read
+            .store(self.ring.written.load(Ordering::Acquire), Ordering::Release);
+        self.ring.wake_writer();
+    }
+}
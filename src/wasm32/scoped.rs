@@ -7,7 +7,7 @@ use std::{
     },
 };
 
-use super::{signal::Signal, utils::is_web_worker_thread, Builder, JoinInner};
+use super::{signal::Signal, utils::is_web_worker_thread, Builder, CancellationToken, JoinInner, Transfer};
 
 /// A scope to spawn scoped threads in.
 ///
@@ -40,6 +40,7 @@ pub(crate) struct ScopeData {
     num_running_threads: AtomicUsize,
     a_thread_panicked: AtomicBool,
     signal: Signal,
+    cancellation_token: CancellationToken,
 }
 
 impl ScopeData {
@@ -65,6 +66,29 @@ impl ScopeData {
     }
 }
 
+// Counts a thread as running in a scope until it is released. A thread is released when its packet is dropped, or
+// when it is terminated, as its web worker then never drops the packet. Only the first release counts.
+pub(crate) struct ScopeMember {
+    data: Arc<ScopeData>,
+    released: AtomicBool,
+}
+
+impl ScopeMember {
+    pub(crate) fn new(data: Arc<ScopeData>) -> Self {
+        data.increment_num_running_threads();
+        Self {
+            data,
+            released: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn release(&self, panic: bool) {
+        if !self.released.swap(true, Ordering::AcqRel) {
+            self.data.decrement_num_running_threads(panic);
+        }
+    }
+}
+
 /// Create a scope for spawning scoped threads.
 ///
 /// The function passed to `scope` will be provided a [`Scope`] object,
@@ -83,27 +107,19 @@ impl ScopeData {
 /// If you want to handle panics from spawned threads,
 /// [`join`][ScopedJoinHandle::join] them before the end of the scope.
 ///
-/// On wasm, this will panic on main thread because blocking join is not allowed.
+/// Scoped threads that are terminated, e.g. because they trapped with `panic=abort`, count as panicked.
+///
+/// On wasm, this will panic on main thread because blocking join is not allowed. Use [`scope_async`] there.
 pub fn scope<'env, F, T>(f: F) -> T
 where
     F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
 {
     // Fail early to avoid flaky panics that depend on execution time
     if !is_web_worker_thread() {
-        panic!("scope is not allowed on the main thread");
+        panic!("scope is not allowed on the main thread, use scope_async instead");
     }
 
-    // We put the `ScopeData` into an `Arc` so that other threads can finish their
-    // `decrement_num_running_threads` even after this function returns.
-    let scope = Scope {
-        data: Arc::new(ScopeData {
-            num_running_threads: AtomicUsize::new(0),
-            a_thread_panicked: AtomicBool::new(false),
-            signal: Signal::new(),
-        }),
-        env: PhantomData,
-        scope: PhantomData,
-    };
+    let scope = Scope::new();
 
     // Run `f`, but catch panics so we can make sure to wait for all the threads to join.
     let result = catch_unwind(AssertUnwindSafe(|| f(&scope)));
@@ -113,17 +129,66 @@ where
         scope.data.signal.wait();
     }
 
-    // Throw any panic from `f`, or the return value of `f` if no thread panicked.
-    match result {
-        Err(e) => resume_unwind(e),
-        Ok(_) if scope.data.a_thread_panicked.load(Ordering::Relaxed) => {
-            panic!("a scoped thread panicked")
-        }
-        Ok(result) => result,
+    scope.finish(result)
+}
+
+/// Create a scope for spawning scoped threads, and wait asynchronously for them to finish.
+///
+/// This is the equivalent of [`scope`] for the main thread, where blocking is not allowed. `f` spawns the scoped
+/// threads, and the returned future resolves once all of them have finished, panicking if any of them panicked and
+/// was not joined. It works in web workers too.
+///
+/// # Safety
+///
+/// The scoped threads borrow data for `'env`, but a future cannot make sure that it is run to completion. The caller
+/// must poll the returned future until it resolves, and must not drop or [forget](std::mem::forget) it before. Dropping
+/// it early ends the borrows while threads are still using the data. Awaiting it directly satisfies this, unless the
+/// enclosing future is dropped in turn.
+pub async unsafe fn scope_async<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    let scope = Scope::new();
+
+    // Run `f`, but catch panics so we can make sure to wait for all the threads to join.
+    let result = catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+    // Wait until all the threads are finished.
+    while scope.data.num_running_threads.load(Ordering::Acquire) != 0 {
+        scope.data.signal.wait_async().await;
     }
+
+    scope.finish(result)
 }
 
 impl<'scope, 'env> Scope<'scope, 'env> {
+    fn new() -> Self {
+        // We put the `ScopeData` into an `Arc` so that other threads can finish their
+        // `decrement_num_running_threads` even after the scope has ended.
+        Scope {
+            data: Arc::new(ScopeData {
+                num_running_threads: AtomicUsize::new(0),
+                a_thread_panicked: AtomicBool::new(false),
+                signal: Signal::new(),
+                cancellation_token: CancellationToken::new(),
+            }),
+            env: PhantomData,
+            scope: PhantomData,
+        }
+    }
+
+    // Throws any panic from the scope function, or returns its result if no thread panicked. Must only be called once
+    // all threads have finished.
+    fn finish<T>(&self, result: std::thread::Result<T>) -> T {
+        match result {
+            Err(e) => resume_unwind(e),
+            Ok(_) if self.data.a_thread_panicked.load(Ordering::Relaxed) => {
+                panic!("a scoped thread panicked")
+            }
+            Ok(result) => result,
+        }
+    }
+
     /// Spawns a new thread within a scope, returning a [`ScopedJoinHandle`] for it.
     ///
     /// Unlike non-scoped threads, threads spawned with this function may
@@ -155,6 +220,21 @@ impl<'scope, 'env> Scope<'scope, 'env> {
     {
         Builder::new().spawn_scoped(self, f).expect("failed to spawn thread")
     }
+
+    /// Cancels all threads spawned in this scope, so that the scope ends as soon as they have stopped.
+    ///
+    /// Scoped threads may borrow data from outside the scope, so they cannot be terminated without waiting for them.
+    /// Instead, each scoped thread receives a child of the scope's [cancellation token](super::cancellation_token),
+    /// which this method cancels. Threads that check their token return early, after which [`scope`] returns
+    /// promptly. Threads spawned in this scope after the call start out cancelled.
+    pub fn abort_all(&self) {
+        self.data.cancellation_token.cancel();
+    }
+
+    /// Returns `true` if [Self::abort_all] has been called on this scope.
+    pub fn is_aborted(&self) -> bool {
+        self.data.cancellation_token.is_cancelled()
+    }
 }
 
 impl Builder {
@@ -162,8 +242,11 @@ impl Builder {
     ///
     /// Unlike [Scope::spawn], this method yields an [std::io::Result] to
     /// capture any failure to create the thread at the OS level.
+    ///
+    /// Unless a token is set with [Builder::cancellation_token], the thread receives a child of the scope's token, so
+    /// that it is cancelled by [Scope::abort_all].
     pub fn spawn_scoped<'scope, 'env, F, T>(
-        self,
+        mut self,
         scope: &'scope Scope<'scope, 'env>,
         f: F,
     ) -> std::io::Result<ScopedJoinHandle<'scope, T>>
@@ -171,17 +254,32 @@ impl Builder {
         F: FnOnce() -> T + Send + 'scope,
         T: Send + 'scope,
     {
+        if self.cancellation_token.is_none() {
+            self.cancellation_token = Some(scope.data.cancellation_token.child_token());
+        }
+
         Ok(ScopedJoinHandle(unsafe {
-            self.spawn_unchecked_(f, Some(scope.data.clone()))
+            self.spawn_unchecked_(f, Some(scope.data.clone()), false, Transfer::default())
         }?))
     }
 }
 
 impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    /// Extracts a handle to the underlying thread.
+    pub fn thread(&self) -> &super::Thread {
+        &self.0.thread
+    }
+
+    /// Waits for the associated thread to finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, because blocking waits are not allowed there. Use [Self::join_async] instead.
     pub fn join(self) -> super::Result<T> {
         self.0.join()
     }
 
+    /// Waits for the associated thread to finish asynchronously.
     pub async fn join_async(self) -> super::Result<T> {
         self.0.join_async().await
     }
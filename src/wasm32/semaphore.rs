@@ -0,0 +1,207 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use super::{
+    mutex::Waiter,
+    utils::{is_web_worker_thread, SpinLockMutex},
+};
+
+/// A fair counting semaphore that can be awaited on the main thread and in web workers.
+///
+/// Limits how many callers hold a permit at the same time, e.g. to bound the number of threads using a resource.
+/// Like [AsyncMutex](super::AsyncMutex), acquiring never blocks the main thread: [Self::acquire] suspends the calling
+/// task, and web workers that are not running async code can block in [Self::acquire_blocking] instead. Waiting tasks
+/// and threads are queued, and a released permit is handed directly to the one that has waited longest.
+pub struct AsyncSemaphore {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    permits: usize,
+    // Callers waiting for a permit, in the order they will receive one
+    queue: VecDeque<Arc<Waiter>>,
+}
+
+impl AsyncSemaphore {
+    /// Creates a semaphore with `permits` available permits.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                permits,
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Acquires a permit, suspending the calling task until one is available.
+    ///
+    /// The caller is queued when it is first polled. Dropping the future gives up its place in the queue, or passes the
+    /// permit on if it had already been handed over.
+    pub fn acquire(&self) -> AsyncSemaphoreAcquire<'_> {
+        AsyncSemaphoreAcquire {
+            semaphore: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires a permit, blocking the current web worker until one is available.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the main thread, which cannot block. Use [Self::acquire] there.
+    pub fn acquire_blocking(&self) -> AsyncSemaphorePermit<'_> {
+        if !is_web_worker_thread() {
+            panic!("`AsyncSemaphore::acquire_blocking` cannot be called on the main thread, use `acquire` instead");
+        }
+
+        if let Some(waiter) = self.enqueue() {
+            waiter.wait();
+        }
+        AsyncSemaphorePermit { semaphore: self }
+    }
+
+    /// Acquires a permit if one is available and nobody is waiting for it.
+    pub fn try_acquire(&self) -> Option<AsyncSemaphorePermit<'_>> {
+        let mut state = self.state.lock_spin().unwrap();
+        if state.permits == 0 || !state.queue.is_empty() {
+            return None;
+        }
+        state.permits -= 1;
+        Some(AsyncSemaphorePermit { semaphore: self })
+    }
+
+    /// Returns the number of permits that are currently available.
+    pub fn available_permits(&self) -> usize {
+        self.state.lock_spin().unwrap().permits
+    }
+
+    /// Adds `permits` permits to the semaphore, handing them to waiting callers first.
+    pub fn add_permits(&self, permits: usize) {
+        for _ in 0..permits {
+            self.release();
+        }
+    }
+
+    // Takes a permit if one is available and nobody is waiting, and returns `None`. Otherwise returns a new waiter at
+    // the end of the queue.
+    fn enqueue(&self) -> Option<Arc<Waiter>> {
+        let mut state = self.state.lock_spin().unwrap();
+        if state.permits > 0 && state.queue.is_empty() {
+            state.permits -= 1;
+            return None;
+        }
+        let waiter = Arc::new(Waiter::default());
+        state.queue.push_back(waiter.clone());
+        Some(waiter)
+    }
+
+    // Hands a permit to the longest waiting caller, or makes it available if nobody is waiting
+    fn release(&self) {
+        let next = {
+            let mut state = self.state.lock_spin().unwrap();
+            let next = state.queue.pop_front();
+            match &next {
+                Some(next) => next.grant(),
+                None => state.permits += 1,
+            }
+            next
+        };
+        if let Some(next) = next {
+            next.wake();
+        }
+    }
+}
+
+impl fmt::Debug for AsyncSemaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock_spin().unwrap();
+        f.debug_struct("AsyncSemaphore")
+            .field("permits", &state.permits)
+            .field("waiting", &state.queue.len())
+            .finish()
+    }
+}
+
+/// Future returned by [AsyncSemaphore::acquire].
+pub struct AsyncSemaphoreAcquire<'a> {
+    semaphore: &'a AsyncSemaphore,
+    // Place in the queue, once the future has been polled and no permit was available
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl<'a> Future for AsyncSemaphoreAcquire<'a> {
+    type Output = AsyncSemaphorePermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<AsyncSemaphorePermit<'a>> {
+        let semaphore = self.semaphore;
+        let waiter = match &self.waiter {
+            Some(waiter) => waiter.clone(),
+            None => match semaphore.enqueue() {
+                Some(waiter) => {
+                    self.waiter = Some(waiter.clone());
+                    waiter
+                }
+                None => return Poll::Ready(AsyncSemaphorePermit { semaphore }),
+            },
+        };
+
+        if waiter.poll_granted(cx) {
+            self.waiter = None;
+            Poll::Ready(AsyncSemaphorePermit { semaphore })
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for AsyncSemaphoreAcquire<'_> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            let mut state = self.semaphore.state.lock_spin().unwrap();
+            if waiter.is_granted() {
+                // The permit was handed over, but nobody will use it
+                drop(state);
+                self.semaphore.release();
+            } else {
+                state.queue.retain(|queued| !Arc::ptr_eq(queued, &waiter));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AsyncSemaphoreAcquire<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncSemaphoreAcquire")
+            .field("queued", &self.waiter.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A permit of an [AsyncSemaphore], which is released when dropped.
+pub struct AsyncSemaphorePermit<'a> {
+    semaphore: &'a AsyncSemaphore,
+}
+
+impl AsyncSemaphorePermit<'_> {
+    /// Releases the permit, handing it to the longest waiting caller. Same as dropping it.
+    pub fn release(self) {}
+}
+
+impl Drop for AsyncSemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+impl fmt::Debug for AsyncSemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncSemaphorePermit").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,67 @@
+use std::{any::Any, fmt, io, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Builder, JoinHandle, Result};
+
+impl Builder {
+    /// Spawns a new thread that runs `f` with `arg`, passing both `arg` and the result as JSON, and returns an
+    /// [std::io::Result] to its [SerdeJoinHandle].
+    ///
+    /// `arg` is serialized on the spawning thread and deserialized on the new thread, and the result the other way
+    /// around, so only their JSON text crosses threads. Neither has to be `Send`, as long as it round-trips through
+    /// serde. Spawning fails if `arg` cannot be serialized, and a thread whose result cannot be serialized panics.
+    /// Requires the `serde` feature.
+    pub fn spawn_serde<A, R, F>(self, arg: A, f: F) -> io::Result<SerdeJoinHandle<R>>
+    where
+        A: Serialize + DeserializeOwned,
+        R: Serialize + DeserializeOwned,
+        F: FnOnce(A) -> R,
+        F: Send + 'static,
+    {
+        let arg = serde_json::to_string(&arg).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let handle = self.spawn(move || {
+            let arg = serde_json::from_str(&arg).expect("failed to deserialize the thread argument");
+            serde_json::to_string(&f(arg)).expect("failed to serialize the thread result")
+        })?;
+
+        Ok(SerdeJoinHandle {
+            handle,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// An owned permission to join on a thread spawned with [Builder::spawn_serde], deserializing its result.
+pub struct SerdeJoinHandle<R> {
+    handle: JoinHandle<String>,
+    // The result only crosses threads as JSON, so the handle is `Send` regardless of `R`
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R: DeserializeOwned> SerdeJoinHandle<R> {
+    /// Waits for the associated thread to finish, and deserializes its result.
+    ///
+    /// Like [JoinHandle::join], this must not be called on the main thread. Fails with a [serde_json::Error] payload
+    /// if the result cannot be deserialized.
+    pub fn join(self) -> Result<R> {
+        deserialize(self.handle.join())
+    }
+
+    /// Asynchronously waits for the associated thread to finish, and deserializes its result.
+    ///
+    /// Fails with a [serde_json::Error] payload if the result cannot be deserialized.
+    pub async fn join_async(self) -> Result<R> {
+        deserialize(self.handle.join_async().await)
+    }
+}
+
+impl<R> fmt::Debug for SerdeJoinHandle<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerdeJoinHandle").finish_non_exhaustive()
+    }
+}
+
+fn deserialize<R: DeserializeOwned>(result: Result<String>) -> Result<R> {
+    serde_json::from_str(&result?).map_err(|err| Box::new(err) as Box<dyn Any + Send>)
+}
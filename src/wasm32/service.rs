@@ -0,0 +1,77 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::{
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
+    executor::block_on,
+    StreamExt,
+};
+
+use super::{Builder, JoinHandle};
+
+/// Handle to a thread that processes messages with a handler, see [Builder::spawn_service].
+#[derive(Debug)]
+pub struct ServiceHandle<M, R> {
+    sender: UnboundedSender<(M, oneshot::Sender<R>)>,
+    thread: JoinHandle<()>,
+}
+
+impl<M, R> ServiceHandle<M, R> {
+    pub(crate) fn spawn<H, Fut>(builder: Builder, mut handler: H) -> std::io::Result<Self>
+    where
+        M: Send + 'static,
+        R: Send + 'static,
+        H: FnMut(M) -> Fut,
+        H: Send + 'static,
+        Fut: Future<Output = Option<R>>,
+    {
+        let (sender, mut receiver) = mpsc::unbounded::<(M, oneshot::Sender<R>)>();
+
+        let thread = builder.spawn(move || {
+            block_on(async move {
+                while let Some((msg, reply)) = receiver.next().await {
+                    if let Some(response) = handler(msg).await {
+                        // The sender may not be interested in the reply anymore
+                        let _ = reply.send(response);
+                    }
+                }
+            })
+        })?;
+
+        Ok(Self { sender, thread })
+    }
+
+    /// Sends a message to the service, returning a future that resolves to the reply.
+    ///
+    /// The reply is `None` if the handler did not reply or if the service has stopped.
+    pub fn send(&self, msg: M) -> ServiceReply<R> {
+        let (reply, receiver) = oneshot::channel();
+        // If the service has stopped, the reply sender is dropped and the reply resolves to `None`
+        let _ = self.sender.unbounded_send((msg, reply));
+        ServiceReply(receiver)
+    }
+
+    /// Stops the service once all messages sent so far have been handled, returning a handle to its thread.
+    pub fn stop(self) -> JoinHandle<()> {
+        self.sender.close_channel();
+        self.thread
+    }
+}
+
+/// Future resolving to the reply to a message sent with [ServiceHandle::send].
+#[derive(Debug)]
+pub struct ServiceReply<R>(oneshot::Receiver<R>);
+
+impl<R> Future for ServiceReply<R> {
+    type Output = Option<R>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<R>> {
+        Pin::new(&mut self.0).poll(cx).map(Result::ok)
+    }
+}
@@ -5,6 +5,7 @@ use std::{
         Mutex,
     },
     task::{Poll, Waker},
+    time::Duration,
 };
 
 use futures::future::poll_fn;
@@ -14,11 +15,14 @@ use super::utils::SpinLockMutex;
 /// A combined sync/async synchronization primitive that allows waiting for a condition.
 pub struct Signal {
     waiters: Mutex<Vec<Waker>>,
-    // Starts with 0 and changes to 1 when signaled
+    // Starts with 0, the `SIGNALED` and `INTERRUPTED` bits are set by the respective methods
     value: AtomicU32,
 }
 
 impl Signal {
+    const SIGNALED: u32 = 1;
+    const INTERRUPTED: u32 = 2;
+
     pub fn new() -> Self {
         Self {
             waiters: Mutex::new(Default::default()),
@@ -28,7 +32,16 @@ impl Signal {
 
     /// Sends a signal and unlocks all waiters.
     pub fn signal(&self) {
-        self.value.store(1, Ordering::SeqCst);
+        self.raise(Self::SIGNALED);
+    }
+
+    /// Unlocks all current and future waiters without sending a signal.
+    pub fn interrupt(&self) {
+        self.raise(Self::INTERRUPTED);
+    }
+
+    fn raise(&self, bit: u32) {
+        self.value.fetch_or(bit, Ordering::SeqCst);
 
         // Wake all blocking waiters
         unsafe {
@@ -41,21 +54,53 @@ impl Signal {
         }
     }
 
-    /// Synchronously waits until [Self::signal] is called.
+    /// Returns `true` if [Self::signal] has been called.
+    pub fn is_signaled(&self) -> bool {
+        self.value.load(Ordering::Acquire) & Self::SIGNALED != 0
+    }
+
+    /// Synchronously waits until [Self::signal] or [Self::interrupt] is called.
     pub fn wait(&self) {
-        while self.value.load(Ordering::Relaxed) == 0 {
+        while self.value.load(Ordering::Acquire) == 0 {
             unsafe {
                 wasm32::memory_atomic_wait32(&self.value as *const AtomicU32 as *mut i32, 0, -1);
             }
         }
     }
 
-    /// Asynchronously waits until [Self::signal] is called.
+    /// Synchronously waits until [Self::signal] or [Self::interrupt] is called, or at most `timeout`. Returns `true` if
+    /// either was called.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let timeout = timeout.as_nanos().min(i64::MAX as u128) as i64;
+        // The value only changes once a bit has been raised, which is also the only time waiters are notified
+        if self.value.load(Ordering::Acquire) == 0 {
+            unsafe {
+                wasm32::memory_atomic_wait32(&self.value as *const AtomicU32 as *mut i32, 0, timeout);
+            }
+        }
+        self.value.load(Ordering::Acquire) != 0
+    }
+
+    /// Asynchronously waits until [Self::signal] or [Self::interrupt] is called.
     pub async fn wait_async(&self) {
         poll_fn(|cx| {
             self.waiters.lock_spin().unwrap().push(cx.waker().clone());
 
-            if self.value.load(Ordering::Relaxed) == 1 {
+            if self.value.load(Ordering::Acquire) != 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Asynchronously waits until [Self::signal] is called, ignoring [Self::interrupt].
+    pub async fn wait_signaled_async(&self) {
+        poll_fn(|cx| {
+            self.waiters.lock_spin().unwrap().push(cx.waker().clone());
+
+            if self.is_signaled() {
                 Poll::Ready(())
             } else {
                 Poll::Pending
@@ -0,0 +1,56 @@
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use super::utils;
+
+// Spawns in a single main thread task above which a warning is logged
+const THRESHOLD: usize = 16;
+
+thread_local! {
+    // Spawns since the current task started, or `None` if there were none yet. Only used on the main thread.
+    static SPAWNS_IN_TASK: Cell<Option<usize>> = const { Cell::new(None) };
+    // Depth of `exempt` guards, under which spawns are not counted
+    static EXEMPT: Cell<usize> = const { Cell::new(0) };
+}
+
+// Counts a spawn on the main thread, and warns once the spawns of the current task exceed the threshold
+pub(crate) fn record_spawn() {
+    if utils::is_web_worker_thread() || EXEMPT.with(Cell::get) > 0 {
+        return;
+    }
+
+    let spawns = match SPAWNS_IN_TASK.with(Cell::get) {
+        Some(spawns) => spawns + 1,
+        None => {
+            // The count is reset by a microtask, which runs once the current task has returned to the event loop
+            let reset = Closure::once_into_js(|| SPAWNS_IN_TASK.with(|spawns| spawns.set(None)));
+            utils::then_with_function(&js_sys::Promise::resolve(&JsValue::UNDEFINED), &reset);
+            1
+        }
+    };
+    SPAWNS_IN_TASK.with(|count| count.set(Some(spawns)));
+
+    if spawns == THRESHOLD + 1 {
+        utils::console_warn(&format!(
+            "wasm_thread: more than {} threads spawned in a single task on the main thread, which blocks the page \
+             while their web workers are created. Consider `Builder::spawn_batch`, `set_spawn_rate_limit` or yielding \
+             to the event loop between spawns.",
+            THRESHOLD
+        ));
+    }
+}
+
+// Stops counting spawns until dropped, for spawning in bulk on purpose
+pub(crate) struct Exempt(());
+
+pub(crate) fn exempt() -> Exempt {
+    EXEMPT.with(|exempt| exempt.set(exempt.get() + 1));
+    Exempt(())
+}
+
+impl Drop for Exempt {
+    fn drop(&mut self) {
+        EXEMPT.with(|exempt| exempt.set(exempt.get() - 1));
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::Termination;
+
+// Counters in shared memory, so that they are updated and read consistently from any thread
+static TOTAL_SPAWNED: AtomicUsize = AtomicUsize::new(0);
+static CURRENTLY_LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK_LIVE: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_PANICKED: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_ABORTED: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the thread counters, see [stats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadStats {
+    total_spawned: usize,
+    currently_live: usize,
+    peak_live: usize,
+    total_completed: usize,
+    total_panicked: usize,
+    total_aborted: usize,
+}
+
+impl ThreadStats {
+    /// Returns the number of threads spawned so far.
+    pub fn total_spawned(&self) -> usize {
+        self.total_spawned
+    }
+
+    /// Returns the number of threads that have been spawned and have not finished yet.
+    pub fn currently_live(&self) -> usize {
+        self.currently_live
+    }
+
+    /// Returns the highest number of threads that were live at the same time.
+    pub fn peak_live(&self) -> usize {
+        self.peak_live
+    }
+
+    /// Returns the number of threads that ran to completion without panicking.
+    pub fn total_completed(&self) -> usize {
+        self.total_completed
+    }
+
+    /// Returns the number of threads that panicked or [trapped](Termination::Trapped).
+    pub fn total_panicked(&self) -> usize {
+        self.total_panicked
+    }
+
+    /// Returns the number of threads that were stopped for any other [Termination] reason.
+    pub fn total_aborted(&self) -> usize {
+        self.total_aborted
+    }
+}
+
+/// Returns a snapshot of the thread counters.
+///
+/// The counters are read one after another while threads keep running, so a snapshot taken during spawns or
+/// completions may be slightly inconsistent, e.g. `currently_live` may briefly disagree with the totals.
+pub fn stats() -> ThreadStats {
+    ThreadStats {
+        total_spawned: TOTAL_SPAWNED.load(Ordering::Relaxed),
+        currently_live: CURRENTLY_LIVE.load(Ordering::Relaxed),
+        peak_live: PEAK_LIVE.load(Ordering::Relaxed),
+        total_completed: TOTAL_COMPLETED.load(Ordering::Relaxed),
+        total_panicked: TOTAL_PANICKED.load(Ordering::Relaxed),
+        total_aborted: TOTAL_ABORTED.load(Ordering::Relaxed),
+    }
+}
+
+pub(crate) fn record_spawned() {
+    TOTAL_SPAWNED.fetch_add(1, Ordering::Relaxed);
+    let live = CURRENTLY_LIVE.fetch_add(1, Ordering::Relaxed) + 1;
+    PEAK_LIVE.fetch_max(live, Ordering::Relaxed);
+}
+
+pub(crate) fn record_completed(panicked: bool) {
+    CURRENTLY_LIVE.fetch_sub(1, Ordering::Relaxed);
+    match panicked {
+        true => TOTAL_PANICKED.fetch_add(1, Ordering::Relaxed),
+        false => TOTAL_COMPLETED.fetch_add(1, Ordering::Relaxed),
+    };
+}
+
+pub(crate) fn record_terminated(reason: Termination) {
+    CURRENTLY_LIVE.fetch_sub(1, Ordering::Relaxed);
+    match reason {
+        Termination::Trapped | Termination::OutOfMemory => TOTAL_PANICKED.fetch_add(1, Ordering::Relaxed),
+        _ => TOTAL_ABORTED.fetch_add(1, Ordering::Relaxed),
+    };
+}
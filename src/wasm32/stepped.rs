@@ -0,0 +1,129 @@
+use std::{
+    arch::wasm32,
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::{
+    future::{self, poll_fn, Either},
+    task::noop_waker_ref,
+};
+
+use super::{utils::SpinLockMutex, Builder, JoinHandle};
+
+// Polls granted by the join handle and completed by the thread. The thread polls its future once for every grant.
+#[derive(Debug, Default)]
+pub(crate) struct Stepper {
+    granted: AtomicU32,
+    polled: AtomicU32,
+    waiters: Mutex<Vec<Waker>>,
+}
+
+impl Stepper {
+    // Blocks the thread until a poll beyond `polled` has been granted
+    fn wait_for_grant(&self, polled: u32) {
+        while self.granted.load(Ordering::Acquire) == polled {
+            unsafe {
+                wasm32::memory_atomic_wait32(&self.granted as *const AtomicU32 as *mut i32, polled as i32, -1);
+            }
+        }
+    }
+
+    fn complete_poll(&self) {
+        self.polled.fetch_add(1, Ordering::AcqRel);
+        for waiter in self.waiters.lock_spin().unwrap().drain(..) {
+            waiter.wake();
+        }
+    }
+
+    // Grants one more poll and waits until it has completed
+    pub(crate) async fn step(&self) {
+        let granted = self.granted.fetch_add(1, Ordering::AcqRel) + 1;
+        unsafe {
+            wasm32::memory_atomic_notify(&self.granted as *const AtomicU32 as *mut i32, 1);
+        }
+
+        poll_fn(|cx| {
+            self.waiters.lock_spin().unwrap().push(cx.waker().clone());
+            if self.polled.load(Ordering::Acquire) >= granted {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl Builder {
+    /// Spawns a new thread that runs the future returned by `f` one poll at a time, as requested by
+    /// [JoinHandle::step].
+    ///
+    /// This is meant for tests only, which can interleave the progress of the thread with assertions
+    /// deterministically. The future is polled with a waker that does nothing, so it only makes progress through
+    /// [JoinHandle::step], and the thread blocks between steps. Requires the `stepped_executor` feature.
+    pub fn spawn_stepped<F, Fut>(self, f: F) -> std::io::Result<JoinHandle<Fut::Output>>
+    where
+        F: FnOnce() -> Fut,
+        F: Send + 'static,
+        Fut: Future,
+        Fut::Output: Send + 'static,
+    {
+        let stepper = Arc::new(Stepper::default());
+
+        let their_stepper = stepper.clone();
+        let mut handle = self.spawn(move || {
+            let mut future = pin!(f());
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mut polled = 0;
+            loop {
+                their_stepper.wait_for_grant(polled);
+                let poll = future.as_mut().poll(&mut cx);
+                polled += 1;
+                their_stepper.complete_poll();
+                if let Poll::Ready(output) = poll {
+                    return output;
+                }
+            }
+        })?;
+
+        handle.0.stepper = Some(stepper);
+        Ok(handle)
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// Polls the future of a thread spawned with [Builder::spawn_stepped] once, and waits until the poll has
+    /// completed.
+    ///
+    /// Returns `true` once the poll has completed, or `false` without polling if the thread is no longer running
+    /// because its future has completed or it has been terminated. Requires the `stepped_executor` feature, and is
+    /// meant for tests only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread was not spawned with [Builder::spawn_stepped].
+    pub async fn step(&self) -> bool {
+        let stepper = self
+            .0
+            .stepper
+            .as_ref()
+            .expect("only threads spawned with `spawn_stepped` can be stepped");
+        if self.0.state.signal.is_signaled() {
+            return false;
+        }
+
+        // The thread may be terminated while the poll is pending
+        let step = pin!(stepper.step());
+        let finished = pin!(self.0.state.signal.wait_async());
+        match future::select(step, finished).await {
+            Either::Left(_) => true,
+            Either::Right(_) => false,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::utils;
+
+// Main thread timestamps of the milestones of a thread, as `f64` bits. Zero means the milestone has not been reached.
+#[derive(Debug, Default)]
+pub(crate) struct Timestamps {
+    created: AtomicU64,
+    ready: AtomicU64,
+    started: AtomicU64,
+    completed: AtomicU64,
+    // Time the thread spent running its closure, measured by the thread itself
+    work: AtomicU64,
+    // Start and end of posting the initialization message, which structured clones it
+    serialization_started: AtomicU64,
+    serialization_completed: AtomicU64,
+}
+
+impl Timestamps {
+    // Records the current time for a milestone. Only call this on the main thread, so that all timestamps share the
+    // same time origin.
+    fn record(slot: &AtomicU64) {
+        slot.store(utils::performance_now().to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_created(&self) {
+        Self::record(&self.created);
+    }
+
+    pub(crate) fn record_ready(&self) {
+        Self::record(&self.ready);
+    }
+
+    pub(crate) fn record_started(&self) {
+        Self::record(&self.started);
+    }
+
+    pub(crate) fn has_started(&self) -> bool {
+        self.started.load(Ordering::Relaxed) != 0
+    }
+
+    pub(crate) fn record_completed(&self) {
+        Self::record(&self.completed);
+    }
+
+    // Records the time spent posting the initialization message, given the time it started
+    pub(crate) fn record_serialization(&self, started: f64) {
+        self.serialization_started.store(started.to_bits(), Ordering::Relaxed);
+        Self::record(&self.serialization_completed);
+    }
+
+    // Records how long the closure ran in milliseconds. Unlike the milestones, this is measured on the thread itself.
+    pub(crate) fn record_work(&self, millis: f64) {
+        self.work.store(millis.to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn work_duration(&self) -> Option<Duration> {
+        match self.work.load(Ordering::Relaxed) {
+            0 => None,
+            bits => Some(Duration::from_secs_f64(f64::from_bits(bits).max(0.0) / 1000.0)),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> ThreadTimings {
+        let load = |slot: &AtomicU64| match slot.load(Ordering::Relaxed) {
+            0 => None,
+            bits => Some(f64::from_bits(bits)),
+        };
+        ThreadTimings {
+            created_at: load(&self.created),
+            ready_at: load(&self.ready),
+            started_at: load(&self.started),
+            completed_at: load(&self.completed),
+            serialization_started_at: load(&self.serialization_started),
+            serialization_completed_at: load(&self.serialization_completed),
+        }
+    }
+}
+
+/// Milestones of a thread, see [JoinHandle::timings](super::JoinHandle::timings).
+///
+/// Timestamps are in milliseconds as returned by `performance.now()` on the main thread, which records them when it
+/// is notified by the web worker. A milestone may therefore be recorded slightly after the join handle has observed
+/// it, e.g. a thread can be joined before its completion has been recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreadTimings {
+    created_at: Option<f64>,
+    ready_at: Option<f64>,
+    started_at: Option<f64>,
+    completed_at: Option<f64>,
+    serialization_started_at: Option<f64>,
+    serialization_completed_at: Option<f64>,
+}
+
+impl ThreadTimings {
+    /// Returns when the web worker was created, which is later than spawning if the worker was queued.
+    pub fn created_at(&self) -> Option<f64> {
+        self.created_at
+    }
+
+    /// Returns when the web worker had instantiated the wasm module and was ready to run the thread.
+    pub fn ready_at(&self) -> Option<f64> {
+        self.ready_at
+    }
+
+    /// Returns when the thread started running its closure.
+    pub fn started_at(&self) -> Option<f64> {
+        self.started_at
+    }
+
+    /// Returns when the thread completed. Not recorded for threads that were terminated or trapped.
+    pub fn completed_at(&self) -> Option<f64> {
+        self.completed_at
+    }
+
+    /// Returns the time it took the web worker to load and instantiate the wasm module.
+    pub fn instantiation_latency(&self) -> Option<f64> {
+        Some(self.ready_at? - self.created_at?)
+    }
+
+    /// Returns the time between the web worker becoming ready and the thread starting to run.
+    pub fn scheduling_latency(&self) -> Option<f64> {
+        Some(self.started_at? - self.ready_at?)
+    }
+
+    /// Returns the time it took to post the initialization message to the web worker, which structured clones its
+    /// values, such as the [init extra value](super::Builder::init_extra), and transfers the transferable ones.
+    ///
+    /// This helps to decide whether large values are better transferred than cloned. Posting happens once the web
+    /// worker has been created, so this is not recorded for threads that are still queued.
+    pub fn serialization_time(&self) -> Option<f64> {
+        Some(self.serialization_completed_at? - self.serialization_started_at?)
+    }
+}
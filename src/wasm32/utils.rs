@@ -1,7 +1,12 @@
 use std::{
+    arch::wasm32,
+    collections::BTreeMap,
     io,
     num::NonZeroUsize,
-    sync::{LockResult, Mutex, MutexGuard, TryLockError},
+    sync::{
+        atomic::{AtomicI32, AtomicUsize, Ordering},
+        LockResult, Mutex, MutexGuard, TryLockError,
+    },
 };
 
 use wasm_bindgen::prelude::*;
@@ -22,10 +27,177 @@ pub fn available_parallelism() -> io::Result<NonZeroUsize> {
     ))
 }
 
+/// Detailed information about the available parallelism, see [available_parallelism_detailed].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelismInfo {
+    total: NonZeroUsize,
+    // Performance and efficiency core counts, if hinted by the browser
+    breakdown: Option<(usize, usize)>,
+}
+
+impl ParallelismInfo {
+    /// Returns the total number of logical cores, the same value as [available_parallelism].
+    pub fn total(&self) -> NonZeroUsize {
+        self.total
+    }
+
+    /// Returns the number of performance cores, if the browser hints at it.
+    pub fn performance_cores(&self) -> Option<usize> {
+        self.breakdown.map(|(performance, _)| performance)
+    }
+
+    /// Returns the number of efficiency cores, if the browser hints at it.
+    pub fn efficiency_cores(&self) -> Option<usize> {
+        self.breakdown.map(|(_, efficiency)| efficiency)
+    }
+}
+
+/// Returns the available parallelism, broken down into performance and efficiency cores where possible.
+///
+/// The breakdown is read from the non-standard `navigator.performanceCores` and `navigator.efficiencyCores` hints.
+/// It is only reported if both hints are present and add up to the total, so most browsers only report the total.
+pub fn available_parallelism_detailed() -> io::Result<ParallelismInfo> {
+    let total = available_parallelism()?;
+
+    let navigator = js_sys::Reflect::get(&js_sys::global(), &"navigator".into()).unwrap_or(JsValue::UNDEFINED);
+    let hint = |name: &str| {
+        js_sys::Reflect::get(&navigator, &name.into())
+            .ok()
+            .and_then(|value| value.as_f64())
+            .filter(|count| *count >= 0.0 && count.fract() == 0.0)
+            .map(|count| count as usize)
+    };
+    let breakdown = match (hint("performanceCores"), hint("efficiencyCores")) {
+        (Some(performance), Some(efficiency)) if performance + efficiency == total.get() => {
+            Some((performance, efficiency))
+        }
+        _ => None,
+    };
+
+    Ok(ParallelismInfo { total, breakdown })
+}
+
+/// Returns the current size of the shared wasm memory in 64 KiB pages.
+///
+/// Memory grown by any thread is immediately accessible from all other threads, as they share the same memory. The
+/// `wasm_bindgen` glue re-acquires its JS views of the memory when they are next used after it has grown, so long-lived
+/// workers never operate on stale views.
+pub fn current_memory_pages() -> usize {
+    wasm32::memory_size::<0>()
+}
+
+// Memory size in pages at which spawning is refused, or `usize::MAX` for no limit
+static MEMORY_SOFT_LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Sets a soft limit on the size of the shared wasm memory in 64 KiB pages, or removes it with `None`.
+///
+/// Once memory has grown to the limit, spawning new threads fails with [io::ErrorKind::OutOfMemory]. The limit does
+/// not stop running threads from allocating, it only prevents new threads from adding to the memory pressure.
+pub fn set_memory_soft_limit(pages: Option<usize>) {
+    MEMORY_SOFT_LIMIT.store(pages.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Fails if memory has grown to the limit set by [set_memory_soft_limit].
+pub fn check_memory_soft_limit() -> io::Result<()> {
+    let limit = MEMORY_SOFT_LIMIT.load(Ordering::Relaxed);
+    let pages = current_memory_pages();
+    if pages >= limit {
+        return Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "memory has grown to {} pages, reaching the soft limit of {} pages",
+                pages, limit
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Deepest nesting of threads that may be spawned, or `usize::MAX` for no limit
+static MAX_SPAWN_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Limits how deeply threads spawned by this crate may be nested, or removes the limit with `None`.
+///
+/// Threads spawned from the main thread, or from a web worker that was not spawned by this crate, have a depth of 1,
+/// and threads spawned from within a thread one more than that thread, see
+/// [WorkerConfigView::spawn_depth](super::WorkerConfigView::spawn_depth). Spawning
+/// a thread deeper than `depth` fails with [io::ErrorKind::Other], which guards against threads that recursively
+/// spawn further threads without end.
+pub fn set_max_spawn_depth(depth: Option<usize>) {
+    MAX_SPAWN_DEPTH.store(depth.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Fails if a thread at `depth` would be nested deeper than the limit set by [set_max_spawn_depth].
+pub fn check_spawn_depth(depth: usize) -> io::Result<()> {
+    let limit = MAX_SPAWN_DEPTH.load(Ordering::Relaxed);
+    if depth > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "spawning a thread at depth {} exceeds the maximum spawn depth of {}",
+                depth, limit
+            ),
+        ));
+    }
+    Ok(())
+}
+
 pub fn is_web_worker_thread() -> bool {
     js_sys::eval("self").unwrap().dyn_into::<WorkerGlobalScope>().is_ok()
 }
 
+/// Returns `true` if the running module can share its memory with web workers, i.e. spawn threads.
+///
+/// This requires the module to be built with the `atomics` target feature, so that its memory is backed by a
+/// `SharedArrayBuffer`. Unlike checking whether the browser supports `SharedArrayBuffer`, this detects builds of the
+/// same code without atomics, which cannot spawn threads.
+pub fn atomics_enabled() -> bool {
+    cfg!(target_feature = "atomics")
+        && wasm_bindgen::memory()
+            .dyn_into::<js_sys::WebAssembly::Memory>()
+            .map_or(false, |memory| {
+                memory.buffer().is_instance_of::<js_sys::SharedArrayBuffer>()
+            })
+}
+
+/// Returns `true` if the browser can transfer a `ReadableStream` to another thread.
+pub fn can_transfer_streams() -> bool {
+    thread_local! {
+        static SUPPORTED: bool = js_sys::eval(include_str!("js/transfer_streams.js"))
+            .ok()
+            .and_then(|supported| supported.as_bool())
+            .unwrap_or(false);
+    }
+
+    SUPPORTED.with(|supported| *supported)
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// Schedules `handler` to run after `timeout` milliseconds, returning an id for [clear_timeout].
+    #[wasm_bindgen(js_name = setTimeout)]
+    pub fn set_timeout(handler: &js_sys::Function, timeout: i32) -> i32;
+
+    /// Cancels a timer previously scheduled with [set_timeout].
+    #[wasm_bindgen(js_name = clearTimeout)]
+    pub fn clear_timeout(id: i32);
+
+    /// Returns a high resolution timestamp in milliseconds.
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    pub fn performance_now() -> f64;
+
+    /// Logs a warning to the console.
+    #[wasm_bindgen(js_namespace = console, js_name = warn)]
+    pub fn console_warn(message: &str);
+}
+
+/// Calls `handler` once `promise` has been fulfilled, like [js_sys::Promise::then] but with a plain function, e.g. one
+/// created with [Closure::once_into_js].
+pub fn then_with_function(promise: &js_sys::Promise, handler: &JsValue) {
+    let then: js_sys::Function = js_sys::Reflect::get(promise, &"then".into()).unwrap().unchecked_into();
+    then.call1(promise, handler).unwrap();
+}
+
 #[cfg(feature = "es_modules")]
 #[wasm_bindgen(module = "/src/wasm32/js/module_workers_polyfill.min.js")]
 extern "C" {
@@ -35,25 +207,109 @@ extern "C" {
 /// Extracts path of the `wasm_bindgen` generated .js shim script.
 ///
 /// Internally, this intentionally generates a javascript exception to obtain a stacktrace containing the current script
-/// URL.
-pub fn get_wasm_bindgen_shim_script_path() -> String {
+/// URL. Fails with an error of kind [io::ErrorKind::NotFound] if the path cannot be found in the stack trace.
+pub fn get_wasm_bindgen_shim_script_path() -> io::Result<String> {
+    detect_wasm_bindgen_shim_script_path()
+}
+
+// Extracts the shim path from a stack trace, which depends on browser and `wasm_bindgen` internals
+fn detect_wasm_bindgen_shim_script_path() -> io::Result<String> {
     js_sys::eval(include_str!("js/script_path.js"))
-        .unwrap()
-        .as_string()
-        .unwrap()
+        .ok()
+        .and_then(|path| path.as_string())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "could not determine the wasm_bindgen shim URL, set it with `Builder::wasm_bindgen_shim_url`",
+            )
+        })
 }
 
-/// Generates worker entry script as URL encoded blob
-pub fn get_worker_script(wasm_bindgen_shim_url: Option<String>) -> String {
-    // Cache URL so that subsequent calls are less expensive
-    static CACHED_URL: Mutex<Option<String>> = Mutex::new(None);
+// Shim URLs registered with `register_shim_version`, by version
+static SHIM_VERSIONS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Registers the URL of the `wasm_bindgen` shim script for a build version, to be selected with
+/// [Builder::shim_version](super::Builder::shim_version).
+///
+/// This is useful when several builds are deployed side by side, because a worker must load the shim of the build
+/// whose memory it shares. Registering a version again replaces its URL.
+pub fn register_shim_version(version: String, url: String) {
+    SHIM_VERSIONS.lock_spin().unwrap().insert(version, url);
+}
+
+/// Returns the shim URL registered for `version`.
+pub fn shim_version_url(version: &str) -> io::Result<String> {
+    SHIM_VERSIONS.lock_spin().unwrap().get(version).cloned().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no wasm_bindgen shim URL registered for version `{}`", version),
+        )
+    })
+}
 
-    if let Some(url) = CACHED_URL.lock_spin().unwrap().clone() {
-        return url;
+/// Resolves the URL of the `wasm_bindgen` shim script to load in web workers.
+///
+/// An explicitly set `url` must be absolute. Otherwise the URL is detected once and cached.
+pub fn resolve_wasm_bindgen_shim_url(url: Option<&str>) -> io::Result<String> {
+    static DETECTED_URL: Mutex<Option<String>> = Mutex::new(None);
+
+    let invalid = |url: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("wasm_bindgen shim URL `{}` is not a valid absolute URL", url),
+        )
+    };
+
+    match url {
+        Some(url) => absolute_url(url).map_err(|_| invalid(url)),
+        None => {
+            let mut detected = DETECTED_URL.lock_spin().unwrap();
+            if let Some(url) = detected.as_ref() {
+                return Ok(url.clone());
+            }
+
+            let url = detect_wasm_bindgen_shim_script_path()?;
+            let url = absolute_url(&url).map_err(|_| invalid(&url))?;
+            *detected = Some(url.clone());
+            Ok(url)
+        }
     }
+}
 
-    // If wasm bindgen shim url is not provided, try to obtain one automatically
-    let wasm_bindgen_shim_url = wasm_bindgen_shim_url.unwrap_or_else(get_wasm_bindgen_shim_script_path);
+/// Checks that `url` is a valid absolute URL and returns it in normalized form.
+pub fn absolute_url(url: &str) -> io::Result<String> {
+    Url::new(url).map(|url| url.href()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` is not a valid absolute URL", url),
+        )
+    })
+}
+
+/// Generates worker entry script as URL encoded blob
+///
+/// If `wasm_bindgen_shim_url` is not provided, it is detected like [get_wasm_bindgen_shim_script_path], which fails if
+/// the shim cannot be found.
+pub fn get_worker_script(wasm_bindgen_shim_url: Option<String>) -> io::Result<String> {
+    let wasm_bindgen_shim_url = match wasm_bindgen_shim_url {
+        Some(url) => url,
+        None => detect_wasm_bindgen_shim_script_path()?,
+    };
+    Ok(worker_script(wasm_bindgen_shim_url, &[]))
+}
+
+/// Generates worker entry script as URL encoded blob, which imports the given classic scripts before the shim.
+///
+/// The script URLs must have been normalized with [absolute_url].
+pub fn worker_script(wasm_bindgen_shim_url: String, import_scripts: &[String]) -> String {
+    // Cache URLs so that subsequent calls are less expensive. Keyed by shim URL and imported scripts.
+    type Key = (String, Vec<String>);
+    static CACHED_URLS: Mutex<BTreeMap<Key, String>> = Mutex::new(BTreeMap::new());
+
+    let key = (wasm_bindgen_shim_url, import_scripts.to_vec());
+    if let Some(url) = CACHED_URLS.lock_spin().unwrap().get(&key) {
+        return url.clone();
+    }
 
     // Generate script from template
     #[cfg(feature = "es_modules")]
@@ -61,7 +317,12 @@ pub fn get_worker_script(wasm_bindgen_shim_url: Option<String>) -> String {
     #[cfg(not(feature = "es_modules"))]
     let template = include_str!("js/web_worker.js");
 
-    let script = template.replace("WASM_BINDGEN_SHIM_URL", &wasm_bindgen_shim_url);
+    // Normalized URLs have quotes percent-encoded, so they can be embedded as is
+    let mut script = String::new();
+    for url in import_scripts {
+        script.push_str(&format!("importScripts(\"{}\");\n", url));
+    }
+    script.push_str(&template.replace("WASM_BINDGEN_SHIM_URL", &key.0));
 
     // Create url encoded blob
     let arr = js_sys::Array::new();
@@ -74,7 +335,7 @@ pub fn get_worker_script(wasm_bindgen_shim_url: Option<String>) -> String {
     )
     .unwrap();
 
-    *CACHED_URL.lock_spin().unwrap() = Some(url.clone());
+    CACHED_URLS.lock_spin().unwrap().insert(key, url.clone());
 
     url
 }
@@ -83,6 +344,8 @@ pub fn get_worker_script(wasm_bindgen_shim_url: Option<String>) -> String {
 ///
 /// Atomic wait panics in wasm main thread so we can't use `Mutex::lock()`.
 /// This is a helper, which implement spinlock by calling `Mutex::try_lock()` in a loop.
+/// Contended locks back off exponentially between attempts, parking the thread for short periods where atomic wait
+/// is available.
 /// Care must be taken not to introduce deadlocks when using this trait.
 pub trait SpinLockMutex {
     type Inner;
@@ -94,12 +357,54 @@ impl<T> SpinLockMutex for Mutex<T> {
     type Inner = T;
 
     fn lock_spin<'a>(&'a self) -> LockResult<MutexGuard<'a, Self::Inner>> {
+        let mut backoff = Backoff::new();
         loop {
             match self.try_lock() {
                 Ok(guard) => break Ok(guard),
-                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::WouldBlock) => backoff.snooze(),
                 Err(TryLockError::Poisoned(e)) => break Err(e),
             }
         }
     }
 }
+
+thread_local! {
+    // Atomic wait is only allowed in web workers
+    static CAN_PARK: bool = is_web_worker_thread();
+}
+
+/// Bounded exponential backoff for spin loops.
+///
+/// The first few rounds only spin. After that, web workers park for exponentially growing periods, while the main
+/// thread, which cannot park, keeps spinning.
+struct Backoff {
+    step: u32,
+}
+
+impl Backoff {
+    /// Rounds that only spin, doubling the number of spins each time.
+    const SPIN_LIMIT: u32 = 6;
+    /// Upper bound on the park duration, as a power of two in nanoseconds (about 1ms).
+    const PARK_LIMIT: u32 = 20;
+
+    fn new() -> Self {
+        Self { step: 0 }
+    }
+
+    fn snooze(&mut self) {
+        if self.step <= Self::SPIN_LIMIT || !CAN_PARK.with(|can_park| *can_park) {
+            for _ in 0..1 << self.step.min(Self::SPIN_LIMIT) {
+                std::hint::spin_loop();
+            }
+        } else {
+            // Nobody notifies this location, so this just sleeps until the timeout
+            let location = AtomicI32::new(0);
+            let timeout_ns = 1i64 << self.step.min(Self::PARK_LIMIT);
+            unsafe {
+                wasm32::memory_atomic_wait32(location.as_ptr(), 0, timeout_ns);
+            }
+        }
+
+        self.step = self.step.saturating_add(1);
+    }
+}
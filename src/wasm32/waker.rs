@@ -0,0 +1,52 @@
+use std::task::Waker;
+
+use super::{utils::is_web_worker_thread, WorkerMessage};
+
+/// A handle that wakes a main thread task from within a web worker.
+///
+/// Calling [Waker::wake] directly from a web worker runs the waker's logic on that worker. A `CrossThreadWaker`
+/// instead posts the waker to the main thread, which wakes the task from its own event loop. This is the same
+/// mechanism that the crate uses internally to relay spawn requests.
+///
+/// # Dispatcher
+///
+/// Wakeups are routed through the main thread message handler that the crate registers for every worker it spawns.
+/// Waking from a worker that was not spawned by this crate, or from a thread that has already been terminated, has no
+/// effect on the task. When called on the main thread, the waker is woken directly.
+///
+/// # Reentrancy
+///
+/// Wakeups posted from a worker are dispatched after the main thread returns to its event loop, so the task is never
+/// woken while the main thread is inside a call into Rust. Waking on the main thread itself wakes the task
+/// synchronously, as [Waker::wake] would.
+#[derive(Debug, Clone)]
+pub struct CrossThreadWaker {
+    waker: Waker,
+}
+
+impl CrossThreadWaker {
+    /// Creates a new cross-thread waker from the waker of a main thread task.
+    pub fn new(waker: Waker) -> Self {
+        Self { waker }
+    }
+
+    /// Wakes the task, consuming this waker.
+    pub fn wake(self) {
+        if is_web_worker_thread() {
+            WorkerMessage::Wake(self.waker).post();
+        } else {
+            self.waker.wake();
+        }
+    }
+
+    /// Wakes the task without consuming this waker.
+    pub fn wake_by_ref(&self) {
+        self.clone().wake();
+    }
+}
+
+impl From<Waker> for CrossThreadWaker {
+    fn from(waker: Waker) -> Self {
+        Self::new(waker)
+    }
+}
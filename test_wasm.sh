@@ -1,4 +1,8 @@
 #!/bin/sh
+set -e
 
-RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals' \
-	wasm-pack test --headless --firefox -- -Z build-std=panic_abort,std
+export RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals'
+
+wasm-pack test --headless --firefox -- -Z build-std=panic_abort,std
+# The doctests of the wasm32 module are `compile_fail` examples, which only need to be compiled
+cargo test --doc --target wasm32-unknown-unknown -Z doctest-xcompile -Z build-std=panic_abort,std
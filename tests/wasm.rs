@@ -1,15 +1,30 @@
 #![cfg(target_arch = "wasm32")]
 
 use core::{
-    sync::atomic::{AtomicBool, Ordering},
+    future::IntoFuture,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     time::Duration,
 };
+use std::sync::Arc;
 
+use futures::stream::{FuturesUnordered, StreamExt};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use wasm_bindgen_test::*;
 use wasm_thread as thread;
 
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
+async fn sleep_async(duration: Duration) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, duration.as_millis() as i32)
+            .unwrap();
+    });
+    JsFuture::from(promise).await.unwrap();
+}
+
 #[wasm_bindgen_test]
 async fn thread_join_async() {
     let handle = thread::spawn(|| 1234);
@@ -17,6 +32,19 @@ async fn thread_join_async() {
     assert_eq!(handle.join_async().await.unwrap(), 1234);
 }
 
+#[wasm_bindgen_test]
+async fn thread_join_futures_unordered() {
+    let mut futures: FuturesUnordered<_> = (0..50).map(|i| thread::spawn(move || i * 2).into_future()).collect();
+
+    let mut results = vec![];
+    while let Some(result) = futures.next().await {
+        results.push(result.unwrap());
+    }
+
+    results.sort();
+    assert_eq!(results, (0..50).map(|i| i * 2).collect::<Vec<_>>());
+}
+
 #[wasm_bindgen_test]
 async fn thread_join_sync() {
     // synchronous join only allowed inside threads
@@ -30,6 +58,12 @@ async fn thread_join_sync() {
     .unwrap();
 }
 
+#[wasm_bindgen_test]
+#[should_panic(expected = "blocking join is not allowed on the main thread")]
+fn thread_join_sync_main_thread() {
+    thread::spawn(|| 1234).join().unwrap();
+}
+
 #[wasm_bindgen_test]
 async fn thread_scope_sync() {
     // synchronous scope only allowed inside threads
@@ -98,6 +132,44 @@ async fn thread_scope_sync_block() {
     .unwrap();
 }
 
+#[wasm_bindgen_test]
+async fn thread_scope_async() {
+    let numbers: Vec<u32> = (1..=100).collect();
+    let sum = AtomicUsize::new(0);
+
+    // SAFETY: the future is awaited to completion
+    unsafe {
+        thread::scope_async(|s| {
+            for chunk in numbers.chunks(25) {
+                let sum = &sum;
+                s.spawn(move || {
+                    thread::sleep(Duration::from_millis(10));
+                    sum.fetch_add(chunk.iter().sum::<u32>() as usize, Ordering::Relaxed);
+                });
+            }
+        })
+        .await;
+    }
+
+    // The scope ends once all threads are done with the borrowed data
+    assert_eq!(sum.into_inner(), 5050);
+}
+
+#[wasm_bindgen_test]
+async fn thread_scope_unjoined_thread_panics() {
+    let result = thread::spawn(|| {
+        thread::scope(|s| {
+            // Traps with `panic=abort`, and is never joined
+            s.spawn(|| panic!("scoped thread panicked"));
+        });
+    })
+    .join_async()
+    .await;
+
+    // The scope re-panics in the outer thread instead of waiting forever
+    assert!(result.is_err());
+}
+
 #[wasm_bindgen_test]
 async fn thread_async_channel() {
     // Exchange a series of messages over async channel.
@@ -121,3 +193,2322 @@ async fn thread_async_channel() {
     let result = main_rx.recv().await.unwrap();
     assert_eq!(result, "Hello world!");
 }
+
+#[wasm_bindgen_test]
+async fn thread_max_lifetime() {
+    let handle = thread::Builder::new()
+        .max_lifetime(Duration::from_millis(100))
+        .spawn(|| loop {
+            thread::sleep(Duration::from_millis(10));
+        })
+        .unwrap();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::LifetimeExceeded)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_startup_timeout() {
+    // A worker script that never runs the thread, like one stuck loading
+    let source = js_sys::Array::of1(&"".into());
+    let blob = web_sys::Blob::new_with_str_sequence(&source).unwrap();
+    let script_url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
+
+    let handle = thread::Builder::new()
+        .worker_script_url(script_url)
+        .startup_timeout(Duration::from_millis(100))
+        .max_lifetime(Duration::from_secs(10))
+        .spawn(|| {})
+        .unwrap();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::StartupTimedOut)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_startup_timeout_not_applied_to_work() {
+    // The thread starts in time, so only the work deadline fires
+    let handle = thread::Builder::new()
+        .startup_timeout(Duration::from_secs(10))
+        .max_lifetime(Duration::from_millis(500))
+        .spawn(|| loop {
+            thread::sleep(Duration::from_millis(10));
+        })
+        .unwrap();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::LifetimeExceeded)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_max_lifetime_not_exceeded() {
+    let handle = thread::Builder::new()
+        .max_lifetime(Duration::from_secs(10))
+        .spawn(|| 1234)
+        .unwrap();
+
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_module_bytes() {
+    // wasm-bindgen places the module next to its shim script
+    let shim_path = thread::get_wasm_bindgen_shim_script_path().unwrap();
+    let module_path = format!("{}_bg.wasm", shim_path.strip_suffix(".js").unwrap());
+
+    let response: web_sys::Response = JsFuture::from(web_sys::window().unwrap().fetch_with_str(&module_path))
+        .await
+        .unwrap()
+        .into();
+    let buffer = JsFuture::from(response.array_buffer().unwrap()).await.unwrap();
+    let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+
+    let handle = thread::Builder::new().with_module_bytes(&bytes).spawn(|| 1234).unwrap();
+
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_find_workers_by_tag() {
+    let (release_tx, release_rx) = async_channel::unbounded::<()>();
+
+    let handles: Vec<_> = [7001, 7001, 7002]
+        .iter()
+        .map(|&tag| {
+            let release_rx = release_rx.clone();
+            thread::Builder::new()
+                .tag(tag)
+                .spawn(move || {
+                    let _ = futures::executor::block_on(release_rx.recv());
+                })
+                .unwrap()
+        })
+        .collect();
+
+    assert_eq!(thread::find_workers_by_tag(7001).len(), 2);
+    let tagged = thread::find_workers_by_tag(7002);
+    assert_eq!(tagged.len(), 1);
+    assert_eq!(tagged[0].tag(), Some(7002));
+    assert!(thread::find_workers_by_tag(7003).is_empty());
+
+    release_tx.close();
+    for handle in handles {
+        handle.join_async().await.unwrap();
+    }
+}
+
+#[wasm_bindgen_test]
+async fn thread_pause_resume() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let handle = {
+        let counter = counter.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                counter.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now();
+            }
+        })
+    };
+
+    while counter.load(Ordering::Relaxed) == 0 {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+
+    handle.pause();
+    // Let the thread reach its next yield point
+    sleep_async(Duration::from_millis(50)).await;
+    let paused_at = counter.load(Ordering::Relaxed);
+    sleep_async(Duration::from_millis(100)).await;
+    assert_eq!(counter.load(Ordering::Relaxed), paused_at);
+
+    handle.resume();
+    sleep_async(Duration::from_millis(100)).await;
+    assert!(counter.load(Ordering::Relaxed) > paused_at);
+
+    stop.store(true, Ordering::Relaxed);
+    handle.join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_completion_order() {
+    struct DropRecorder(Arc<AtomicUsize>);
+
+    impl Drop for DropRecorder {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..20)
+        .map(|i| {
+            let recorder = DropRecorder(dropped.clone());
+            thread::spawn(move || {
+                let _ = &recorder;
+                i
+            })
+        })
+        .collect();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join_async().await.unwrap(), i);
+        // Captured data is released before the result is published
+        assert!(dropped.load(Ordering::SeqCst) > i);
+    }
+}
+
+#[cfg(not(feature = "es_modules"))]
+#[wasm_bindgen_test]
+async fn thread_import_scripts() {
+    let source = js_sys::Array::of1(&"self.wasm_thread_helper = 42;".into());
+    let blob = web_sys::Blob::new_with_str_sequence(&source).unwrap();
+    let helper_url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
+
+    let handle = thread::Builder::new()
+        .import_scripts(vec![helper_url])
+        .spawn(|| {
+            js_sys::Reflect::get(&js_sys::global(), &"wasm_thread_helper".into())
+                .unwrap()
+                .as_f64()
+        })
+        .unwrap();
+
+    assert_eq!(handle.join_async().await.unwrap(), Some(42.0));
+}
+
+#[wasm_bindgen_test]
+fn thread_import_scripts_invalid_url() {
+    let result = thread::Builder::new()
+        .import_scripts(vec!["relative/helper.js".to_string()])
+        .spawn(|| {});
+
+    assert!(result.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn thread_memory_soft_limit() {
+    assert!(thread::current_memory_pages() > 0);
+
+    thread::set_memory_soft_limit(Some(thread::current_memory_pages()));
+    let err = thread::Builder::new().spawn(|| 1234);
+    thread::set_memory_soft_limit(None);
+    assert_eq!(err.unwrap_err().kind(), std::io::ErrorKind::OutOfMemory);
+
+    let handle = thread::Builder::new().spawn(|| 1234).unwrap();
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_max_spawn_depth() {
+    // Spawns nested threads until spawning fails, and returns the depth of the deepest thread
+    fn nest() -> usize {
+        let depth = thread::current_worker_config().unwrap().spawn_depth();
+        match thread::Builder::new().spawn(nest) {
+            Ok(handle) => handle.join().unwrap(),
+            Err(err) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::Other);
+                depth
+            }
+        }
+    }
+
+    thread::set_max_spawn_depth(Some(3));
+    let handle = thread::Builder::new().spawn(nest).unwrap();
+    let depth = handle.join_async().await;
+    thread::set_max_spawn_depth(None);
+    assert_eq!(depth.unwrap(), 3);
+}
+
+#[wasm_bindgen_test]
+async fn thread_ported_std_builder() {
+    // Code written against `std::thread`, with only the import swapped
+    fn sum_in_threads() -> std::io::Result<u32> {
+        use wasm_thread as thread;
+
+        let handles = (0..4)
+            .map(|i| {
+                thread::Builder::new()
+                    .name(format!("summand-{}", i))
+                    .stack_size(1 << 20)
+                    .spawn(move || i * 10)
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(handles.into_iter().map(|handle| handle.join().unwrap()).sum())
+    }
+
+    // Blocking joins are not allowed on the main thread
+    let handle = thread::spawn(sum_in_threads);
+    assert_eq!(handle.join_async().await.unwrap().unwrap(), 60);
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_timed() {
+    let handle = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(100));
+        1234
+    });
+    let (result, duration) = handle.join_timed().await;
+    assert_eq!(result.unwrap(), 1234);
+    assert!(duration >= Duration::from_millis(100), "{:?}", duration);
+
+    let handle = thread::spawn(|| loop {
+        thread::sleep(Duration::from_millis(10));
+    });
+    handle.abort();
+    let (result, duration) = handle.join_timed().await;
+    assert!(result.is_err());
+    assert_eq!(duration, Duration::ZERO);
+}
+
+#[cfg(not(feature = "es_modules"))]
+#[wasm_bindgen_test]
+async fn thread_memory_mismatch_refused() {
+    // A worker script that enters the thread with a context beyond its memory, as if it had received a smaller one
+    let script = format!(
+        "importScripts('{}');
+        self.onmessage = event => {{
+            wasm_bindgen(event.data.module, event.data.memory)
+                .then(wasm => wasm.wasm_thread_entry_point(0xfffffff0))
+                .catch(err => setTimeout(() => {{ throw err; }}));
+        }};",
+        thread::get_wasm_bindgen_shim_script_path().unwrap()
+    );
+    let source = js_sys::Array::of1(&script.into());
+    let blob = web_sys::Blob::new_with_str_sequence(&source).unwrap();
+    let script_url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
+
+    let ran = Arc::new(AtomicBool::new(false));
+    let their_ran = ran.clone();
+    let handle = thread::Builder::new()
+        .worker_script_url(script_url)
+        .spawn(move || their_ran.store(true, Ordering::SeqCst))
+        .unwrap();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Trapped)
+    );
+    assert!(!ran.load(Ordering::SeqCst));
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_interval() {
+    let interval = Duration::from_millis(100);
+    let (mut results, stop) = thread::Builder::new()
+        .spawn_interval(interval, || async { js_sys::Date::now() })
+        .unwrap();
+
+    let mut times = Vec::new();
+    for _ in 0..3 {
+        times.push(results.next().await.unwrap());
+    }
+    for pair in times.windows(2) {
+        // Allow for timer coarsening
+        assert!(pair[1] - pair[0] >= 95.0, "{:?}", times);
+    }
+
+    stop.stop().join_async().await.unwrap();
+    // Results of runs before the stop may still be queued, but the stream ends
+    while results.next().await.is_some() {}
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_detailed() {
+    let result = thread::spawn(|| 1234).join_detailed_async().await;
+    assert_eq!(result.unwrap(), 1234);
+
+    // With `panic=abort`, panics trap
+    let result = thread::spawn(|| panic!("thread panicked")).join_detailed_async().await;
+    assert!(matches!(result, Err(thread::JoinError::Trapped)), "{:?}", result);
+
+    let handle = thread::spawn(|| loop {
+        thread::sleep(Duration::from_millis(10));
+    });
+    handle.abort();
+    let result = handle.join_detailed_async().await;
+    assert!(matches!(result, Err(thread::JoinError::Aborted)), "{:?}", result);
+
+    let result = thread::Builder::new()
+        .max_lifetime(Duration::from_millis(100))
+        .spawn(|| loop {
+            thread::sleep(Duration::from_millis(10));
+        })
+        .unwrap()
+        .join_detailed_async()
+        .await;
+    assert!(matches!(result, Err(thread::JoinError::TimedOut)), "{:?}", result);
+
+    // Larger than the memory can grow to, but small enough to be a valid allocation
+    let result = thread::spawn(|| Vec::<u8>::with_capacity(isize::MAX as usize - (1 << 16)).capacity())
+        .join_detailed_async()
+        .await;
+    assert!(matches!(result, Err(thread::JoinError::OutOfMemory)), "{:?}", result);
+}
+
+#[wasm_bindgen_test]
+async fn thread_shared_once() {
+    static INIT: thread::SharedOnce = thread::SharedOnce::new();
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            thread::spawn(|| {
+                INIT.call_once(|| {
+                    // Keeps the initialization running while the other threads arrive
+                    thread::sleep(Duration::from_millis(100));
+                    RUNS.fetch_add(1, Ordering::SeqCst);
+                });
+                INIT.is_completed()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert!(handle.join_async().await.unwrap());
+    }
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+
+    INIT.call_once_async(|| unreachable!()).await;
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+}
+
+#[wasm_bindgen_test]
+async fn thread_boxed_trait_object() {
+    trait Shape {
+        fn area(&self) -> f64;
+    }
+
+    #[derive(Debug)]
+    struct Square(f64);
+
+    impl Shape for Square {
+        fn area(&self) -> f64 {
+            self.0 * self.0
+        }
+    }
+
+    let handle = thread::spawn(|| Box::new(Square(3.0)) as Box<dyn Shape + Send>);
+    assert_eq!(handle.join_async().await.unwrap().area(), 9.0);
+
+    let handle = thread::spawn(|| Box::new(Square(2.0)) as Box<dyn std::any::Any + Send>);
+    let square = handle.join_boxed::<Square>().await.unwrap().ok().unwrap();
+    assert_eq!(square.area(), 4.0);
+
+    let handle = thread::spawn(|| Box::new(1234u32) as Box<dyn std::any::Any + Send>);
+    let value = handle.join_boxed::<Square>().await.unwrap().unwrap_err();
+    assert_eq!(value.downcast_ref::<u32>(), Some(&1234));
+}
+
+#[wasm_bindgen_test]
+async fn thread_detached_completes() {
+    let (tx, rx) = async_channel::unbounded::<u32>();
+
+    // Drop the handle right away, the worker must stay alive until it has finished
+    drop(thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        futures::executor::block_on(tx.send(1234)).unwrap();
+    }));
+
+    assert_eq!(rx.recv().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_join() {
+    let result = thread::spawn(|| thread::spawn_join(|| 6 * 7).unwrap())
+        .join_async()
+        .await
+        .unwrap();
+
+    assert_eq!(result, 42);
+}
+
+#[wasm_bindgen_test]
+#[should_panic(expected = "spawn_join is not allowed on the main thread")]
+fn thread_spawn_join_main_thread() {
+    let _ = thread::spawn_join(|| 6 * 7);
+}
+
+#[wasm_bindgen_test]
+async fn thread_default_builder_contention() {
+    // `Builder::new` and `Builder::set_default` lock the global default builder
+    let handle = thread::spawn(|| {
+        for _ in 0..1000 {
+            thread::Builder::new().set_default();
+        }
+    });
+
+    for i in 0..1000 {
+        thread::Builder::new().set_default();
+        if i % 100 == 0 {
+            sleep_async(Duration::from_millis(1)).await;
+        }
+    }
+
+    handle.join_async().await.unwrap();
+    thread::Builder::empty().set_default();
+}
+
+#[wasm_bindgen_test]
+async fn thread_cross_thread_waker() {
+    let woken = Arc::new(AtomicBool::new(false));
+    let mut spawned = false;
+
+    futures::future::poll_fn(|cx| {
+        if woken.load(Ordering::Acquire) {
+            return core::task::Poll::Ready(());
+        }
+
+        if !spawned {
+            spawned = true;
+            let waker = thread::CrossThreadWaker::new(cx.waker().clone());
+            let woken = woken.clone();
+            thread::spawn(move || {
+                woken.store(true, Ordering::Release);
+                waker.wake();
+            });
+        }
+
+        core::task::Poll::Pending
+    })
+    .await;
+
+    assert!(woken.load(Ordering::Acquire));
+}
+
+#[wasm_bindgen_test]
+async fn thread_inherit_cancel() {
+    let token = thread::CancellationToken::new();
+
+    let handle = thread::Builder::new()
+        .cancellation_token(token.clone())
+        .spawn(|| {
+            let child = thread::Builder::new()
+                .inherit_cancel(true)
+                .spawn(|| {
+                    let token = thread::cancellation_token().unwrap();
+                    token.wait();
+                    token.is_cancelled()
+                })
+                .unwrap();
+
+            let token = thread::cancellation_token().unwrap();
+            token.wait();
+            (token.is_cancelled(), child.join().unwrap())
+        })
+        .unwrap();
+
+    sleep_async(Duration::from_millis(100)).await;
+    assert!(!token.is_cancelled());
+    token.cancel();
+
+    assert_eq!(handle.join_async().await.unwrap(), (true, true));
+}
+
+#[wasm_bindgen_test]
+fn thread_child_token_cancelled() {
+    let parent = thread::CancellationToken::new();
+    let child = parent.child_token();
+
+    child.cancel();
+    assert!(!parent.is_cancelled());
+
+    let child = parent.child_token();
+    parent.cancel();
+    assert!(child.is_cancelled());
+    assert!(parent.child_token().is_cancelled());
+}
+
+#[wasm_bindgen_test]
+fn thread_available_parallelism_detailed() {
+    let info = thread::available_parallelism_detailed().unwrap();
+    assert_eq!(info.total(), thread::available_parallelism().unwrap());
+
+    if let (Some(performance), Some(efficiency)) = (info.performance_cores(), info.efficiency_cores()) {
+        assert_eq!(performance + efficiency, info.total().get());
+    }
+}
+
+#[wasm_bindgen_test]
+async fn thread_progress_reporter() {
+    let (mut reporter, stream) = thread::ProgressReporter::new();
+
+    thread::spawn(move || {
+        for i in 1..=1000 {
+            reporter.report(i as f32 / 1000.0);
+        }
+    });
+
+    let updates: Vec<f32> = stream.collect().await;
+    assert!(updates.len() < 1000);
+    assert!(updates.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(updates.last(), Some(&1.0));
+}
+
+#[wasm_bindgen_test]
+async fn thread_retryable() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let mut task = thread::Builder::new().spawn_retryable(|| {
+        let attempts = attempts.clone();
+        move || {
+            if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                panic!("first attempt fails");
+            }
+            42
+        }
+    });
+
+    assert!(task.run().await.is_err());
+    assert_eq!(task.retry().await.unwrap().unwrap(), 42);
+    assert_eq!(task.retries(), 1);
+    assert_eq!(attempts.load(Ordering::Relaxed), 2);
+}
+
+#[wasm_bindgen_test]
+async fn thread_retryable_exhausted() {
+    let mut task = thread::Builder::new()
+        .max_lifetime(Duration::from_millis(100))
+        .spawn_retryable(|| {
+            || loop {
+                thread::sleep(Duration::from_millis(10));
+            }
+        })
+        .max_retries(1);
+
+    assert!(task.run().await.is_err());
+    assert!(task.retry().await.unwrap().is_err());
+    assert!(task.retry().await.is_none());
+}
+
+#[wasm_bindgen_test]
+async fn thread_shutdown() {
+    let tokens: Vec<_> = (0..3).map(|_| thread::CancellationToken::new()).collect();
+    let cooperative: Vec<_> = tokens[..2]
+        .iter()
+        .map(|token| {
+            thread::Builder::new()
+                .cancellation_token(token.clone())
+                .spawn(|| {
+                    let token = thread::cancellation_token().unwrap();
+                    token.wait();
+                    token.is_cancelled()
+                })
+                .unwrap()
+        })
+        .collect();
+    // Opted into cancellation, but never checks its token
+    let oblivious = thread::Builder::new()
+        .cancellation_token(tokens[2].clone())
+        .spawn(|| loop {
+            thread::sleep(Duration::from_millis(10));
+        })
+        .unwrap();
+    // Not cancellable, so left to finish
+    let uncancellable = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(200));
+        1234
+    });
+
+    sleep_async(Duration::from_millis(100)).await;
+    thread::shutdown();
+    thread::restart();
+
+    assert!(tokens.iter().all(thread::CancellationToken::is_cancelled));
+    // Terminated before their token is cancelled, whether they check it or not
+    for handle in cooperative {
+        let err = handle.join_async().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<thread::Termination>(),
+            Some(&thread::Termination::Terminated)
+        );
+    }
+    let err = oblivious.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Terminated)
+    );
+    assert_eq!(uncancellable.join_async().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_terminate_all_workers() {
+    let handle = thread::spawn(|| loop {
+        thread::sleep(Duration::from_millis(10));
+    });
+
+    sleep_async(Duration::from_millis(100)).await;
+    thread::terminate_all_workers();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Terminated)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_duplicate_entry_ignored() {
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let handle = {
+        let runs = runs.clone();
+        thread::spawn(move || {
+            runs.fetch_add(1, Ordering::Relaxed);
+            // Simulate a duplicate initialization reaching the entry point. The guard returns before the context
+            // pointer is touched.
+            thread::wasm_thread_entry_point(0);
+            1234
+        })
+    };
+
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+    assert_eq!(runs.load(Ordering::Relaxed), 1);
+}
+
+#[wasm_bindgen_test]
+async fn thread_service() {
+    let mut requests = 0;
+    let service = thread::Builder::new()
+        .spawn_service(move |increment: usize| {
+            requests += 1;
+            let reply = (requests, increment + 1);
+            async move { Some(reply) }
+        })
+        .unwrap();
+
+    for i in 0..5 {
+        assert_eq!(service.send(i * 10).await, Some((i + 1, i * 10 + 1)));
+    }
+
+    service.stop().join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_unchecked_borrow() {
+    let data = vec![1, 2, 3];
+
+    // Threads that may borrow data are not terminated, so the join handle only resolves once the closure is done
+    let handle = unsafe {
+        thread::Builder::new()
+            .max_lifetime(Duration::from_millis(10))
+            .spawn_unchecked(|| {
+                thread::sleep(Duration::from_millis(100));
+                data.iter().sum::<i32>()
+            })
+            .unwrap()
+    };
+
+    assert_eq!(handle.join_async().await.unwrap(), 6);
+    drop(data);
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_interrupt() {
+    let (interrupter_tx, interrupter_rx) = async_channel::bounded(1);
+    let (release_tx, release_rx) = async_channel::unbounded::<()>();
+
+    let handle = thread::spawn(move || {
+        // The child does not complete until the end of the test
+        let child = thread::spawn(move || {
+            let _ = futures::executor::block_on(release_rx.recv());
+        });
+        interrupter_tx.try_send(child.interrupter()).unwrap();
+
+        let err = child.join().unwrap_err();
+        err.downcast_ref::<thread::JoinInterrupted>().is_some()
+    });
+
+    let interrupter = interrupter_rx.recv().await.unwrap();
+    sleep_async(Duration::from_millis(100)).await;
+    interrupter.interrupt();
+
+    assert!(handle.join_async().await.unwrap());
+    release_tx.close();
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_rate_limit() {
+    thread::set_spawn_rate_limit(core::num::NonZeroU32::new(5));
+    let handles: Vec<_> = (0..20).map(|_| thread::spawn(js_sys::Date::now)).collect();
+
+    let mut started = vec![];
+    for handle in handles {
+        started.push(handle.join_async().await.unwrap());
+    }
+    thread::set_spawn_rate_limit(None);
+
+    // 20 workers at 5 per second are created 200ms apart, so the last one starts about 3.8s after the first
+    let first = started.iter().cloned().fold(f64::INFINITY, f64::min);
+    let last = started.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    assert!(last - first >= 3000.0, "workers started within {}ms", last - first);
+}
+
+#[wasm_bindgen_test]
+async fn thread_current_activity() {
+    let (step_tx, step_rx) = async_channel::unbounded::<()>();
+    let (done_tx, done_rx) = async_channel::unbounded::<()>();
+
+    let handle = thread::spawn(move || {
+        for phase in ["phase 1", "phase 2"].iter() {
+            thread::set_current_activity(phase);
+            done_tx.try_send(()).unwrap();
+            futures::executor::block_on(step_rx.recv()).unwrap();
+        }
+    });
+
+    done_rx.recv().await.unwrap();
+    assert_eq!(handle.current_activity().as_deref(), Some("phase 1"));
+    step_tx.send(()).await.unwrap();
+    done_rx.recv().await.unwrap();
+    assert_eq!(handle.current_activity().as_deref(), Some("phase 2"));
+    step_tx.send(()).await.unwrap();
+
+    handle.join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_soft_deadline() {
+    let cooperative = thread::Builder::new()
+        .soft_deadline(Duration::from_millis(100), Duration::from_secs(10))
+        .spawn(|| {
+            let token = thread::cancellation_token().unwrap();
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            1234
+        })
+        .unwrap();
+    let uncooperative = thread::Builder::new()
+        .soft_deadline(Duration::from_millis(100), Duration::from_millis(100))
+        .spawn(|| loop {
+            thread::sleep(Duration::from_millis(10));
+        })
+        .unwrap();
+
+    assert_eq!(cooperative.join_async().await.unwrap(), 1234);
+    let err = uncooperative.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::LifetimeExceeded)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_async_responsive() {
+    let timer_fired = Arc::new(AtomicBool::new(false));
+
+    let timer = {
+        let timer_fired = timer_fired.clone();
+        wasm_bindgen::closure::Closure::once_into_js(move || timer_fired.store(true, Ordering::Relaxed))
+    };
+    web_sys::window()
+        .unwrap()
+        .set_timeout_with_callback_and_timeout_and_arguments_0(timer.unchecked_ref(), 10)
+        .unwrap();
+
+    let handle = thread::spawn(|| thread::sleep(Duration::from_millis(500)));
+    handle.join_async().await.unwrap();
+
+    // The timer fired while the main thread was waiting for the worker
+    assert!(timer_fired.load(Ordering::Relaxed));
+}
+
+#[wasm_bindgen_test]
+fn thread_invalid_shim_url() {
+    let err = thread::Builder::new()
+        .wasm_bindgen_shim_url("not a url".to_string())
+        .spawn(|| {})
+        .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("wasm_bindgen shim URL `not a url`"));
+}
+
+#[wasm_bindgen_test]
+async fn thread_shim_version() {
+    let shim_path = thread::get_wasm_bindgen_shim_script_path().unwrap();
+    thread::register_shim_version("current".to_string(), shim_path);
+    thread::register_shim_version("other".to_string(), "https://example.invalid/other.js".to_string());
+
+    let handle = thread::Builder::new()
+        .shim_version("current".to_string())
+        .spawn(|| 1234)
+        .unwrap();
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+
+    let err = thread::Builder::new()
+        .shim_version("unknown".to_string())
+        .spawn(|| {})
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[wasm_bindgen_test]
+async fn thread_try_join_all() {
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            thread::Builder::new()
+                .tag(7101)
+                .spawn(move || {
+                    if i == 2 {
+                        panic!("shard failed");
+                    }
+                    loop {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                })
+                .unwrap()
+        })
+        .collect();
+
+    let (index, result) = thread::try_join_all(handles).await.unwrap_err();
+    assert_eq!(index, 2);
+    assert!(result.is_err());
+    // The remaining workers have been terminated
+    assert!(thread::find_workers_by_tag(7101).is_empty());
+}
+
+#[wasm_bindgen_test]
+async fn thread_try_join_all_ok() {
+    let handles: Vec<_> = (0..4).map(|i| thread::spawn(move || i * 2)).collect();
+
+    assert_eq!(thread::try_join_all(handles).await.unwrap(), vec![0, 2, 4, 6]);
+}
+
+#[wasm_bindgen_test]
+async fn thread_map_reduce() {
+    let items: Vec<u64> = (0..10000).collect();
+    let expected: u64 = items.iter().map(|i| i * i).sum();
+
+    let sum = thread::map_reduce(items, 4, |i| i * i, |a, b| a + b, 0).await.unwrap();
+    assert_eq!(sum, expected);
+
+    // A failing shard fails the whole operation
+    let result = thread::map_reduce(
+        (0..8u32).collect(),
+        4,
+        |i| {
+            if i == 5 {
+                core::arch::wasm32::unreachable();
+            }
+            i
+        },
+        |a, b| a + b,
+        0,
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn thread_global_worker_error_handler() {
+    let errors = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+
+    {
+        let errors = errors.clone();
+        thread::set_global_worker_error_handler(move |info, _error| errors.borrow_mut().push(info.tag()));
+    }
+
+    let handle = thread::Builder::new()
+        .tag(7201)
+        .spawn(|| core::arch::wasm32::unreachable())
+        .unwrap();
+
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Trapped)
+    );
+    assert_eq!(*errors.borrow(), vec![Some(7201)]);
+
+    // Later worker errors are no longer reported once the handler is cleared
+    thread::clear_global_worker_error_handler();
+    let handle = thread::spawn(|| core::arch::wasm32::unreachable());
+    assert!(handle.join_async().await.is_err());
+    assert_eq!(*errors.borrow(), vec![Some(7201)]);
+}
+
+#[wasm_bindgen_test]
+async fn thread_checkpoint() {
+    let checkpoint = thread::Checkpoint::<u32>::new();
+    let (saved_tx, saved_rx) = async_channel::unbounded::<()>();
+    let (resume_tx, resume_rx) = async_channel::unbounded::<()>();
+
+    let handle = thread::Builder::new()
+        .with_checkpoint(&checkpoint)
+        .spawn(move || {
+            let checkpoint = thread::Checkpoint::<u32>::current().unwrap();
+            checkpoint.save(1);
+            saved_tx.try_send(()).unwrap();
+            futures::executor::block_on(resume_rx.recv()).unwrap();
+            checkpoint.save(2);
+            panic!("computation failed");
+        })
+        .unwrap();
+
+    saved_rx.recv().await.unwrap();
+    assert_eq!(handle.last_checkpoint::<u32>(), Some(1));
+    assert_eq!(handle.last_checkpoint::<String>(), None);
+    resume_tx.send(()).await.unwrap();
+
+    assert!(handle.join_async().await.is_err());
+    assert_eq!(checkpoint.get(), Some(2));
+}
+
+#[wasm_bindgen_test]
+fn thread_atomics_enabled() {
+    // The tests are built with atomics and shared memory
+    assert!(thread::atomics_enabled());
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_into() {
+    let slot = Arc::new(std::sync::Mutex::new(None));
+
+    let handle = thread::Builder::new()
+        .spawn_into(slot.clone(), || {
+            thread::sleep(Duration::from_millis(50));
+            1234
+        })
+        .unwrap();
+    assert_eq!(*slot.lock().unwrap(), None);
+
+    handle.join_async().await.unwrap();
+    assert_eq!(slot.lock().unwrap().take(), Some(1234));
+}
+
+#[wasm_bindgen_test]
+async fn thread_scope_abort_all() {
+    thread::spawn(|| {
+        let iterations = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| {
+                    let token = thread::cancellation_token().unwrap();
+                    // Would run for ten seconds if not aborted
+                    for _ in 0..1000 {
+                        if token.is_cancelled() {
+                            return;
+                        }
+                        iterations.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                });
+            }
+
+            thread::sleep(Duration::from_millis(100));
+            assert!(!s.is_aborted());
+            s.abort_all();
+            assert!(s.is_aborted());
+        });
+
+        // The threads stopped long before running to completion
+        assert!(iterations.load(Ordering::Relaxed) < 300);
+    })
+    .join_async()
+    .await
+    .unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_stats() {
+    // Other tests may leave threads running, so only lower bounds are checked
+    let before = thread::stats();
+
+    thread::spawn(|| 1234).join_async().await.unwrap();
+
+    let handle = thread::Builder::new()
+        .max_lifetime(Duration::from_millis(50))
+        .spawn(|| thread::sleep(Duration::from_secs(10)))
+        .unwrap();
+    assert!(thread::stats().currently_live() >= 1);
+    handle.join_async().await.unwrap_err();
+
+    let after = thread::stats();
+    assert!(after.total_spawned() >= before.total_spawned() + 2);
+    assert!(after.total_completed() > before.total_completed());
+    assert!(after.total_aborted() > before.total_aborted());
+    assert!(after.peak_live() >= 1);
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_owning() {
+    let data = vec![1u32, 2, 3, 4];
+
+    let handle = thread::Builder::new()
+        .spawn_owning(data, |data| async move {
+            // The future owns the data, so it can hold borrows and non-`Send` values across await points
+            let borrowed = &data[..];
+            let local = std::rc::Rc::new(10);
+            futures::future::ready(()).await;
+            borrowed.iter().sum::<u32>() * *local
+        })
+        .unwrap();
+
+    assert_eq!(handle.join_async().await.unwrap(), 100);
+}
+
+#[wasm_bindgen_test]
+async fn thread_worker_scope_name() {
+    let name = thread::Builder::new()
+        .name("named-worker".to_string())
+        .spawn(|| {
+            js_sys::Reflect::get(&js_sys::global(), &"name".into())
+                .unwrap()
+                .as_string()
+        })
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+
+    assert_eq!(name.as_deref(), Some("named-worker"));
+}
+
+#[wasm_bindgen_test]
+async fn thread_init_extra() {
+    let extra = js_sys::JSON::parse(r#"{"token": "secret"}"#).unwrap();
+
+    let token = thread::Builder::new()
+        .init_extra(extra)
+        .spawn(|| {
+            let extra = thread::init_extra().unwrap();
+            js_sys::Reflect::get(&extra, &"token".into()).unwrap().as_string()
+        })
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+    assert_eq!(token.as_deref(), Some("secret"));
+
+    let extra = thread::spawn(|| thread::init_extra().is_some())
+        .join_async()
+        .await
+        .unwrap();
+    assert!(!extra);
+}
+
+#[cfg(feature = "deadlock_detection")]
+#[wasm_bindgen_test]
+async fn thread_deadlock_detection() {
+    let (outcome_tx, outcome_rx) = async_channel::unbounded();
+
+    // Each thread receives the handle of the other and joins it, reporting whether its join detected the deadlock
+    let join_other = |handle_rx: async_channel::Receiver<thread::JoinHandle<()>>| {
+        let outcome_tx = outcome_tx.clone();
+        move || {
+            let other = futures::executor::block_on(handle_rx.recv()).unwrap();
+            let detected = match other.join() {
+                Ok(()) => false,
+                Err(e) => e.downcast_ref::<thread::DeadlockDetected>().is_some(),
+            };
+            outcome_tx.try_send(detected).unwrap();
+        }
+    };
+    let (handle_tx_a, handle_rx_a) = async_channel::unbounded();
+    let (handle_tx_b, handle_rx_b) = async_channel::unbounded();
+    let a = thread::spawn(join_other(handle_rx_a));
+    let b = thread::spawn(join_other(handle_rx_b));
+    handle_tx_a.try_send(b).unwrap();
+    handle_tx_b.try_send(a).unwrap();
+
+    // Exactly one join fails, which lets the other complete
+    let mut outcomes = vec![outcome_rx.recv().await.unwrap(), outcome_rx.recv().await.unwrap()];
+    outcomes.sort();
+    assert_eq!(outcomes, [false, true]);
+}
+
+#[wasm_bindgen_test]
+async fn thread_critical_cleanup() {
+    let lock = Arc::new(AtomicBool::new(false));
+
+    let their_lock = lock.clone();
+    let cleanup_lock = lock.clone();
+    let handle = thread::Builder::new()
+        .max_lifetime(Duration::from_millis(500))
+        .critical_cleanup(move || cleanup_lock.store(false, Ordering::Release))
+        .spawn(move || {
+            their_lock.store(true, Ordering::Release);
+            // Never gets to release the lock
+            thread::sleep(Duration::from_secs(10));
+            their_lock.store(false, Ordering::Release);
+        })
+        .unwrap();
+
+    while !lock.load(Ordering::Acquire) {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+    assert!(handle.join_async().await.is_err());
+    assert!(!lock.load(Ordering::Acquire));
+
+    // The cleanup does not run for threads that complete
+    let cleaned = Arc::new(AtomicBool::new(false));
+    let their_cleaned = cleaned.clone();
+    thread::Builder::new()
+        .critical_cleanup(move || their_cleaned.store(true, Ordering::Release))
+        .spawn(|| {})
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+    sleep_async(Duration::from_millis(50)).await;
+    assert!(!cleaned.load(Ordering::Acquire));
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_batch_worker_index() {
+    let handles = thread::Builder::new()
+        .spawn_batch(4, || thread::current_worker_index().unwrap())
+        .unwrap();
+
+    let mut indices = Vec::new();
+    for handle in handles {
+        indices.push(handle.join_async().await.unwrap());
+    }
+    assert_eq!(indices, [0, 1, 2, 3]);
+
+    let index = thread::spawn(thread::current_worker_index).join_async().await.unwrap();
+    assert_eq!(index, None);
+}
+
+#[wasm_bindgen_test]
+async fn thread_with_default_scoped() {
+    // Threads spawned with the default builder report the index of the default in effect
+    let spawn = || thread::spawn(thread::current_worker_index);
+
+    let (outer, inner, restored) =
+        thread::Builder::with_default_scoped(thread::Builder::empty().worker_index(1), || {
+            let outer = spawn();
+            let inner = thread::Builder::with_default_scoped(thread::Builder::empty().worker_index(2), spawn);
+            (outer, inner, spawn())
+        });
+    let after = spawn();
+
+    assert_eq!(outer.join_async().await.unwrap(), Some(1));
+    assert_eq!(inner.join_async().await.unwrap(), Some(2));
+    assert_eq!(restored.join_async().await.unwrap(), Some(1));
+    assert_eq!(after.join_async().await.unwrap(), None);
+}
+
+#[wasm_bindgen_test]
+async fn thread_memo_pool() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let their_calls = calls.clone();
+    let pool = thread::MemoPool::new(
+        move |x: u64| {
+            their_calls.fetch_add(1, Ordering::Relaxed);
+            x * x
+        },
+        2,
+    );
+
+    assert_eq!(pool.compute(12).await.unwrap(), 144);
+    let spawned = thread::stats().total_spawned();
+    assert_eq!(pool.compute(12).await.unwrap(), 144);
+    assert_eq!(thread::stats().total_spawned(), spawned);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    // Filling the cache evicts the least recently used result
+    pool.compute(3).await.unwrap();
+    pool.compute(12).await.unwrap();
+    pool.compute(4).await.unwrap();
+    assert_eq!(pool.len(), 2);
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+    pool.compute(12).await.unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+    pool.compute(3).await.unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 4);
+}
+
+#[wasm_bindgen_test]
+async fn thread_panic_channel() {
+    let (mut panics, handle) = thread::Builder::new()
+        .panic_channel(|| -> u32 { panic!("out of band") })
+        .unwrap();
+
+    let report = panics.next().await.unwrap();
+    assert_eq!(report.message(), "out of band");
+    assert!(report.location().unwrap().contains("wasm.rs"));
+
+    // The tests abort on panic, so the thread traps instead of completing with `None`
+    assert!(!matches!(handle.join_async().await, Ok(Some(_))));
+
+    let (mut panics, handle) = thread::Builder::new().panic_channel(|| 42).unwrap();
+    assert_eq!(handle.join_async().await.unwrap(), Some(42));
+    assert_eq!(panics.next().await, None);
+}
+
+#[wasm_bindgen_test]
+async fn thread_oversubscription_policy() {
+    let parallelism = thread::available_parallelism().unwrap().get();
+
+    // Record warnings instead of logging them
+    js_sys::eval(
+        "globalThis.wasm_thread_warnings = []; globalThis.wasm_thread_warn = console.warn; \
+         console.warn = message => wasm_thread_warnings.push(message);",
+    )
+    .unwrap();
+    let handles = thread::Builder::new()
+        .oversubscription_policy(thread::OversubscriptionPolicy::Warn)
+        .spawn_batch(parallelism + 1, || thread::sleep(Duration::from_millis(100)))
+        .unwrap();
+    let warnings = js_sys::eval("console.warn = wasm_thread_warn; wasm_thread_warnings.length").unwrap();
+    assert!(warnings.as_f64().unwrap() >= 1.0);
+    for handle in handles {
+        handle.join_async().await.unwrap();
+    }
+
+    // Web workers beyond the available parallelism are queued until others finish
+    const CAPPED: u64 = 7301;
+    let handles = thread::Builder::new()
+        .tag(CAPPED)
+        .oversubscription_policy(thread::OversubscriptionPolicy::Cap)
+        .spawn_batch(parallelism + 2, || thread::sleep(Duration::from_millis(200)))
+        .unwrap();
+    assert!(thread::find_workers_by_tag(CAPPED).len() <= parallelism);
+    for handle in handles {
+        handle.join_async().await.unwrap();
+    }
+    assert!(thread::find_workers_by_tag(CAPPED).is_empty());
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_with_stream() {
+    let stream: web_sys::ReadableStream =
+        js_sys::eval("new ReadableStream({ start(controller) { controller.enqueue(42); controller.close(); } })")
+            .unwrap()
+            .unchecked_into();
+
+    let (chunk_tx, chunk_rx) = async_channel::bounded(1);
+    thread::Builder::new()
+        // A pooled worker stays alive once the closure returns, so that the read can settle
+        .pool_size(1)
+        .spawn_with_stream(stream.clone(), move |stream| {
+            // The stream arrives unlocked and can be read by the worker
+            assert!(!stream.locked());
+            let reader = stream.get_reader();
+            let read = js_sys::Reflect::get(&reader, &"read".into()).unwrap();
+            let read: js_sys::Promise = read
+                .unchecked_into::<js_sys::Function>()
+                .call0(&reader)
+                .unwrap()
+                .unchecked_into();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = JsFuture::from(read).await.unwrap();
+                let chunk = js_sys::Reflect::get(&result, &"value".into()).unwrap();
+                chunk_tx.send(chunk.as_f64()).await.unwrap();
+            });
+        })
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+
+    assert_eq!(chunk_rx.recv().await.unwrap(), Some(42.0));
+    // The stream has been transferred away
+    assert!(stream.locked());
+    thread::clear_worker_pool();
+}
+
+#[cfg(feature = "stepped_executor")]
+#[wasm_bindgen_test]
+async fn thread_spawn_stepped() {
+    let progress = Arc::new(AtomicUsize::new(0));
+
+    let their_progress = progress.clone();
+    let handle = thread::Builder::new()
+        .spawn_stepped(move || async move {
+            for step in 1..=3 {
+                their_progress.store(step, Ordering::Release);
+                futures::pending!();
+            }
+            their_progress.load(Ordering::Acquire) * 10
+        })
+        .unwrap();
+
+    // Nothing runs until the first step
+    sleep_async(Duration::from_millis(50)).await;
+    assert_eq!(progress.load(Ordering::Acquire), 0);
+
+    for step in 1..=3 {
+        assert!(handle.step().await);
+        assert_eq!(progress.load(Ordering::Acquire), step);
+    }
+
+    // The last step completes the future
+    assert!(handle.step().await);
+    assert!(!handle.step().await);
+    assert_eq!(handle.join_async().await.unwrap(), 30);
+}
+
+#[cfg(feature = "serde")]
+#[wasm_bindgen_test]
+async fn thread_worker_config() {
+    let config = thread::WorkerConfig {
+        name: Some("configured".to_string()),
+        credentials: Some(thread::WorkerCredentials::SameOrigin),
+        ..Default::default()
+    };
+
+    let json = serde_json::to_string(&config).unwrap();
+    let parsed: thread::WorkerConfig = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, config);
+    let partial: thread::WorkerConfig = serde_json::from_str(r#"{"name": "configured"}"#).unwrap();
+    assert_eq!(partial.name, config.name);
+
+    let name = thread::Builder::from_config(parsed)
+        .spawn(|| {
+            js_sys::Reflect::get(&js_sys::global(), &"name".into())
+                .unwrap()
+                .as_string()
+        })
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+    assert_eq!(name.as_deref(), Some("configured"));
+}
+
+#[cfg(feature = "serde")]
+#[wasm_bindgen_test]
+async fn thread_spawn_serde() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Shape {
+        name: String,
+        points: Vec<Point>,
+        parent: Option<Box<Shape>>,
+    }
+
+    let shape = Shape {
+        name: "triangle".to_string(),
+        points: vec![Point { x: 0, y: 0 }, Point { x: 4, y: 0 }, Point { x: 0, y: 3 }],
+        parent: Some(Box::new(Shape {
+            name: "canvas".to_string(),
+            points: vec![],
+            parent: None,
+        })),
+    };
+
+    let moved = thread::Builder::new()
+        .spawn_serde(shape, |mut shape: Shape| {
+            for point in &mut shape.points {
+                point.x += 1;
+            }
+            shape.parent.as_mut().unwrap().name.push_str(" (moved)");
+            shape
+        })
+        .unwrap()
+        .join_async()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        moved,
+        Shape {
+            name: "triangle".to_string(),
+            points: vec![Point { x: 1, y: 0 }, Point { x: 5, y: 0 }, Point { x: 1, y: 3 }],
+            parent: Some(Box::new(Shape {
+                name: "canvas (moved)".to_string(),
+                points: vec![],
+                parent: None,
+            })),
+        }
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_timings() {
+    let handle = thread::spawn(|| thread::sleep(Duration::from_millis(20)));
+    assert_eq!(handle.timings().started_at(), None);
+
+    // Milestones are recorded when the main thread receives the messages of the worker, which may be after joining
+    while handle.timings().completed_at().is_none() {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+    let timings = handle.timings();
+
+    let ready = timings.ready_at().unwrap();
+    let started = timings.started_at().unwrap();
+    assert!(timings.created_at().unwrap() <= ready);
+    assert!(ready <= started);
+    assert!(started <= timings.completed_at().unwrap());
+    assert!(timings.instantiation_latency().unwrap() >= 0.0);
+    assert!(timings.scheduling_latency().unwrap() >= 0.0);
+}
+
+#[wasm_bindgen_test]
+async fn thread_serialization_time() {
+    // Cloned with the initialization message
+    let extra = JsValue::from_str(&"x".repeat(1 << 20));
+    let handle = thread::Builder::new().init_extra(extra).spawn(|| {}).unwrap();
+
+    while handle.timings().started_at().is_none() {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+    assert!(handle.timings().serialization_time().unwrap() >= 0.0);
+    handle.join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_on_discarded() {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+
+    let handle = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(20));
+        1234
+    })
+    .on_discarded(move |result| {
+        sender.send(result.unwrap()).unwrap();
+    });
+    drop(handle);
+
+    assert_eq!(receiver.await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_pipeline() {
+    let results = thread::Pipeline::new(2, || 0..20u32)
+        .unwrap()
+        .stage(|input| input.map(|x| x * 3))
+        .unwrap()
+        .stage(|input| input.filter(|x| x % 2 == 0))
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    let expected: Vec<u32> = (0..20).map(|x| x * 3).filter(|x| x % 2 == 0).collect();
+    assert_eq!(results, expected);
+}
+
+#[wasm_bindgen_test]
+async fn thread_memory_growth_visible_to_running_worker() {
+    // Address of memory grown while the thread is running, or zero until then
+    let address = Arc::new(AtomicUsize::new(0));
+
+    let their_address = address.clone();
+    let handle = thread::spawn(move || {
+        let address = loop {
+            match their_address.load(Ordering::Acquire) {
+                0 => thread::sleep(Duration::from_millis(1)),
+                address => break address,
+            }
+        };
+        // The grown pages are not managed by the allocator, so they are free to use
+        unsafe {
+            (address as *mut u32).write_volatile(1234);
+            (address as *const u32).read_volatile()
+        }
+    });
+
+    sleep_async(Duration::from_millis(20)).await;
+    let previous_pages = core::arch::wasm32::memory_grow::<0>(1);
+    assert_ne!(previous_pages, usize::MAX);
+    address.store(previous_pages * 65536, Ordering::Release);
+
+    assert_eq!(handle.join_async().await.unwrap(), 1234);
+    assert_eq!(
+        unsafe { ((previous_pages * 65536) as *const u32).read_volatile() },
+        1234
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_resumable() {
+    let (sender, receiver) = futures::channel::oneshot::channel::<u32>();
+
+    let handle = thread::Builder::new()
+        .spawn_resumable(move || async move { receiver.await.unwrap() * 2 })
+        .unwrap();
+    // The thread has completed, leaving the future suspended while waiting for the value
+    let suspended = handle.join_async().await.unwrap();
+
+    sender.send(21).unwrap();
+    assert_eq!(suspended.await, 42);
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_resumable_ready() {
+    let handle = thread::Builder::new().spawn_resumable(|| async { 1234 }).unwrap();
+    assert_eq!(handle.join_async().await.unwrap().await, 1234);
+}
+
+#[wasm_bindgen_test]
+fn thread_register_global_closure() {
+    struct CountOnDrop(Arc<AtomicUsize>);
+
+    impl Drop for CountOnDrop {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    for _ in 0..2 {
+        let guard = CountOnDrop(dropped.clone());
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+            let _ = &guard;
+        }) as Box<dyn FnMut()>);
+        thread::register_global_closure(closure);
+    }
+    assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+    thread::shutdown();
+    thread::restart();
+    assert_eq!(dropped.load(Ordering::Relaxed), 2);
+}
+
+#[wasm_bindgen_test]
+async fn thread_current_worker_config() {
+    assert!(thread::current_worker_config().is_none());
+
+    let handle = thread::Builder::new()
+        .name("configured".into())
+        .init_extra(js_sys::JSON::parse(r#"{"answer":42}"#).unwrap())
+        .spawn(|| {
+            let config = thread::current_worker_config().unwrap();
+            let extra = config.init_extra().unwrap();
+            let answer = js_sys::Reflect::get(&extra, &"answer".into()).unwrap().as_f64();
+            (config.name().map(String::from), answer)
+        })
+        .unwrap();
+
+    assert_eq!(
+        handle.join_async().await.unwrap(),
+        (Some("configured".to_string()), Some(42.0))
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_async_mutex_contention() {
+    // Ids of the threads in the order they acquired the lock
+    let mutex = Arc::new(thread::AsyncMutex::new(Vec::new()));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let workers: Vec<_> = (1..=2)
+        .map(|id| {
+            let mutex = mutex.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                // Re-locks right away, which would starve the main thread without fairness
+                while !done.load(Ordering::Relaxed) {
+                    mutex.lock_blocking().push(id);
+                }
+            })
+        })
+        .collect();
+
+    // Wait until both workers contend for the lock
+    while !{
+        let acquired = mutex.lock().await;
+        acquired.contains(&1) && acquired.contains(&2)
+    } {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+
+    for _ in 0..10 {
+        mutex.lock().await.push(0);
+    }
+    done.store(true, Ordering::Relaxed);
+    for worker in workers {
+        worker.join_async().await.unwrap();
+    }
+
+    let acquired = Arc::try_unwrap(mutex).unwrap().into_inner();
+    assert_eq!(acquired.iter().filter(|&&id| id == 0).count(), 10);
+}
+
+#[wasm_bindgen_test]
+async fn thread_async_semaphore_limits_holders() {
+    let semaphore = Arc::new(thread::AsyncSemaphore::new(2));
+    let holders = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..4)
+        .map(|id| {
+            let semaphore = semaphore.clone();
+            let holders = holders.clone();
+            let peak = peak.clone();
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    // Half of the workers wait asynchronously, the other half block
+                    let permit = if id % 2 == 0 {
+                        futures::executor::block_on(semaphore.acquire())
+                    } else {
+                        semaphore.acquire_blocking()
+                    };
+                    let current = holders.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(current, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    holders.fetch_sub(1, Ordering::SeqCst);
+                    permit.release();
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join_async().await.unwrap();
+    }
+
+    assert!(peak.load(Ordering::SeqCst) <= 2);
+    assert_eq!(semaphore.available_permits(), 2);
+    let permit = semaphore.acquire().await;
+    assert_eq!(semaphore.available_permits(), 1);
+    drop(permit);
+    assert_eq!(semaphore.available_permits(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_handle_moved_into_local_task() {
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    let handle = assert_send(thread::spawn(|| 1234));
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        // Held across an await point inside the local task
+        sleep_async(Duration::from_millis(10)).await;
+        let _ = sender.send(handle.join_async().await.unwrap());
+    });
+
+    assert_eq!(receiver.await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_detach_wait_keeps_result() {
+    let handle = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(100));
+        1234
+    });
+    let waiter = handle.detach_wait();
+
+    // Stop waiting before the thread finishes
+    {
+        let wait = waiter.wait();
+        let timeout = sleep_async(Duration::from_millis(10));
+        futures::pin_mut!(wait, timeout);
+        assert!(matches!(
+            futures::future::select(wait, timeout).await,
+            futures::future::Either::Right(_)
+        ));
+    }
+    assert!(!waiter.is_finished());
+    let handle = handle.try_join().unwrap_err();
+
+    waiter.wait().await;
+    assert!(waiter.is_finished());
+    assert_eq!(handle.try_join().unwrap().unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_fold_results() {
+    let chunks: Vec<Vec<u64>> = (0..4).map(|chunk| (chunk * 100..(chunk + 1) * 100).collect()).collect();
+    let expected: u64 = chunks.iter().flatten().sum();
+
+    let handles = chunks
+        .into_iter()
+        .map(|chunk| thread::spawn(move || chunk.iter().sum::<u64>()))
+        .collect();
+    let sum = thread::fold_results(handles, 0, |acc, partial| acc + partial).await;
+
+    assert_eq!(sum.unwrap(), expected);
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_handle_thread() {
+    // Reads the name of the worker global scope
+    fn worker_name() -> Option<String> {
+        js_sys::Reflect::get(&js_sys::global(), &"name".into())
+            .unwrap()
+            .as_string()
+    }
+
+    let named = thread::Builder::new()
+        .name("named".into())
+        .prefix("pool".into())
+        .spawn(worker_name)
+        .unwrap();
+    assert_eq!(named.thread().name(), Some("pool:named"));
+
+    let prefixed = thread::Builder::new().prefix("pool".into()).spawn(worker_name).unwrap();
+    let name = prefixed.thread().name().unwrap().to_string();
+    assert!(name.starts_with("pool:"));
+
+    let unnamed = unsafe { thread::Builder::new().spawn_unchecked(|| ()) }.unwrap();
+    assert_eq!(unnamed.thread().name(), None);
+
+    let ids = [named.thread().id(), prefixed.thread().id(), unnamed.thread().id()];
+    assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+    let thread = prefixed.thread().clone();
+
+    assert_eq!(named.join_async().await.unwrap().as_deref(), Some("pool:named"));
+    assert_eq!(prefixed.join_async().await.unwrap(), Some(name.clone()));
+    unnamed.join_async().await.unwrap();
+    assert_eq!((thread.id(), thread.name()), (ids[1], Some(name.as_str())));
+}
+
+#[wasm_bindgen_test]
+async fn thread_release_module_after_init() {
+    let handle = thread::Builder::new()
+        .release_module_after_init(true)
+        .spawn(|| {
+            // Spawning from the worker still works, as the main thread creates the nested worker
+            let nested = thread::spawn(|| 1000).join().unwrap();
+            (wasm_bindgen::module().is_undefined(), nested + 234)
+        })
+        .unwrap();
+
+    assert_eq!(handle.join_async().await.unwrap(), (true, 1234));
+}
+
+#[wasm_bindgen_test]
+async fn thread_event_bus() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Progress(u32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Finished(&'static str);
+
+    let bus = thread::EventBus::new();
+    let progress = bus.subscribe::<Progress>();
+    let finished = bus.subscribe::<Finished>();
+    let dropped = bus.subscribe::<Progress>();
+    drop(dropped);
+    assert_eq!(bus.subscriber_count::<Progress>(), 1);
+
+    let publishers: Vec<_> = ["first", "second"]
+        .iter()
+        .map(|&name| {
+            let bus = bus.clone();
+            thread::spawn(move || {
+                for step in 0..3 {
+                    assert_eq!(bus.publish(Progress(step)), 1);
+                }
+                bus.publish(Finished(name));
+            })
+        })
+        .collect();
+    for publisher in publishers {
+        publisher.join_async().await.unwrap();
+    }
+
+    let mut progress: Vec<_> = progress.take(6).map(|Progress(step)| step).collect().await;
+    progress.sort();
+    assert_eq!(progress, [0, 0, 1, 1, 2, 2]);
+    let mut finished: Vec<_> = finished.take(2).map(|Finished(name)| name).collect().await;
+    finished.sort();
+    assert_eq!(finished, ["first", "second"]);
+}
+
+#[wasm_bindgen_test]
+async fn thread_worker_pool() {
+    thread_local! {
+        // Counts the threads that ran on the instance of a worker
+        static JOBS: core::cell::Cell<u32> = const { core::cell::Cell::new(0) };
+    }
+
+    // The worker is returned to the pool once the main thread has been notified of the completion
+    async fn wait_for_idle_workers(count: usize) {
+        while thread::idle_worker_count() != count {
+            sleep_async(Duration::from_millis(10)).await;
+        }
+    }
+
+    let job = || {
+        JOBS.with(|jobs| {
+            jobs.set(jobs.get() + 1);
+            jobs.get()
+        })
+    };
+    let pooled = || thread::Builder::new().pool_size(1);
+
+    assert_eq!(pooled().spawn(job).unwrap().join_async().await.unwrap(), 1);
+    wait_for_idle_workers(1).await;
+    // Runs on the same instance, which kept its thread locals
+    assert_eq!(pooled().spawn(job).unwrap().join_async().await.unwrap(), 2);
+    wait_for_idle_workers(1).await;
+
+    // A trapping thread resolves its own handle, and its worker is not returned to the pool
+    let trapped = pooled().spawn(|| panic!("pooled thread panicked")).unwrap();
+    assert_eq!(thread::idle_worker_count(), 0);
+    assert!(trapped.join_async().await.is_err());
+    sleep_async(Duration::from_millis(50)).await;
+    assert_eq!(thread::idle_worker_count(), 0);
+
+    // The next thread gets a fresh worker
+    assert_eq!(pooled().spawn(job).unwrap().join_async().await.unwrap(), 1);
+    wait_for_idle_workers(1).await;
+
+    thread::clear_worker_pool();
+    assert_eq!(thread::idle_worker_count(), 0);
+}
+
+#[wasm_bindgen_test]
+async fn thread_pool_idle_events() {
+    let mut events = thread::idle_events();
+    const POOLED: u64 = 7401;
+    let pooled = || thread::Builder::new().pool_size(1).tag(POOLED);
+
+    let handle = pooled().spawn(|| thread::sleep(Duration::from_millis(50))).unwrap();
+    let id = thread::find_workers_by_tag(POOLED)[0].id();
+    handle.join_async().await.unwrap();
+    assert_eq!(events.next().await, Some(id));
+
+    // The idle worker runs the next job, and becomes idle again under the same identifier
+    assert_eq!(pooled().spawn(|| 1).unwrap().join_async().await.unwrap(), 1);
+    assert_eq!(events.next().await, Some(id));
+
+    thread::clear_worker_pool();
+}
+
+#[wasm_bindgen_test]
+async fn thread_pool_affinity() {
+    thread_local! {
+        // Affinity keys of the threads that ran on the instance of a worker
+        static KEYS: core::cell::RefCell<Vec<u64>> = const { core::cell::RefCell::new(Vec::new()) };
+    }
+
+    let spawn = |key: u64| {
+        thread::Builder::new()
+            .pool_size(2)
+            .affinity(key)
+            .spawn(move || {
+                // Overlap with the other thread, so that each gets its own worker
+                thread::sleep(Duration::from_millis(50));
+                KEYS.with(|keys| {
+                    keys.borrow_mut().push(key);
+                    keys.borrow().clone()
+                })
+            })
+            .unwrap()
+    };
+
+    thread::clear_worker_pool();
+    let (first, second) = (spawn(1), spawn(2));
+    assert_eq!(first.join_async().await.unwrap(), [1]);
+    assert_eq!(second.join_async().await.unwrap(), [2]);
+    while thread::idle_worker_count() != 2 {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+
+    // Spawned in the opposite order, each thread still runs on the worker of its key
+    let (second, first) = (spawn(2), spawn(1));
+    assert_eq!(first.join_async().await.unwrap(), [1, 1]);
+    assert_eq!(second.join_async().await.unwrap(), [2, 2]);
+
+    thread::clear_worker_pool();
+}
+
+#[wasm_bindgen_test]
+async fn thread_drain_worker_pool() {
+    let done = Arc::new(AtomicUsize::new(0));
+    let pooled = || thread::Builder::new().pool_size(2);
+
+    // The handles are dropped, so only draining waits for the threads
+    for _ in 0..8 {
+        let done = done.clone();
+        pooled()
+            .spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                done.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+    }
+
+    let spawn_while_draining = async {
+        assert!(pooled().spawn(|| {}).is_err());
+    };
+    futures::join!(thread::drain_worker_pool(), spawn_while_draining);
+    assert_eq!(done.load(Ordering::Relaxed), 8);
+    assert_eq!(thread::idle_worker_count(), 0);
+
+    // The pool can be used again once drained
+    assert_eq!(pooled().spawn(|| 1).unwrap().join_async().await.unwrap(), 1);
+    thread::clear_worker_pool();
+}
+
+#[wasm_bindgen_test]
+async fn thread_arena() {
+    assert!(thread::with_current_arena(|_| ()).is_none());
+
+    // Returns the address of the first allocation, and how much was allocated before and after
+    let job = || {
+        thread::with_current_arena(|arena| {
+            let before = arena.allocated();
+            let values = arena.alloc_slice_copy(&[1u32, 2, 3]);
+            let name = arena.alloc_str("arena");
+            assert_eq!(values.iter().sum::<u32>(), 6);
+            assert_eq!(name, "arena");
+            assert!(arena.try_alloc([0u8; 1024]).is_err());
+            (values.as_ptr() as usize, before, arena.allocated())
+        })
+        .unwrap()
+    };
+    let pooled = || thread::Builder::new().pool_size(1).with_arena(256);
+
+    let (first, before, after) = pooled().spawn(job).unwrap().join_async().await.unwrap();
+    assert_eq!((before, after), (0, 12 + 5));
+    while thread::idle_worker_count() != 1 {
+        sleep_async(Duration::from_millis(10)).await;
+    }
+    // The next thread on the pooled worker starts with the same region, reset
+    let (second, before, after) = pooled().spawn(job).unwrap().join_async().await.unwrap();
+    assert_eq!((second, before, after), (first, 0, 12 + 5));
+
+    thread::clear_worker_pool();
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_by_token() {
+    // A component that spawns a thread and keeps its handle, and one that only knows its token
+    struct Spawner {
+        handle: thread::JoinHandle<u32>,
+    }
+    async fn wait_for(token: u64) -> std::io::Result<()> {
+        thread::join_by_token(thread::WorkerToken::from_u64(token)).await
+    }
+
+    let spawner = Spawner {
+        handle: thread::spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            1234
+        }),
+    };
+    let token = spawner.handle.token().as_u64();
+
+    wait_for(token).await.unwrap();
+    assert!(spawner.handle.detach_wait().is_finished());
+    // Completed threads can still be waited for while their handle exists
+    wait_for(token).await.unwrap();
+    assert_eq!(spawner.handle.join_async().await.unwrap(), 1234);
+
+    let aborted = thread::spawn(|| loop {
+        thread::sleep(Duration::from_millis(10));
+    });
+    aborted.abort();
+    let err = thread::join_by_token(aborted.token()).await.unwrap_err();
+    assert_eq!(
+        err.get_ref().unwrap().downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Aborted)
+    );
+
+    let err = wait_for(u64::MAX).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[wasm_bindgen_test]
+async fn thread_script_integrity() {
+    // Computes the integrity metadata of the shim, which the bundled worker script imports
+    let digest = js_sys::eval(
+        "(async url => {
+            const content = await (await fetch(url)).arrayBuffer();
+            const digest = new Uint8Array(await crypto.subtle.digest('SHA-256', content));
+            return 'sha256-' + btoa(String.fromCharCode(...digest));
+        })",
+    )
+    .unwrap()
+    .unchecked_into::<js_sys::Function>()
+    .call1(
+        &JsValue::UNDEFINED,
+        &thread::get_wasm_bindgen_shim_script_path().unwrap().into(),
+    )
+    .unwrap();
+    let integrity = JsFuture::from(js_sys::Promise::from(digest))
+        .await
+        .unwrap()
+        .as_string()
+        .unwrap();
+
+    let verified = thread::Builder::new()
+        .script_integrity(integrity)
+        .spawn(|| 1234)
+        .unwrap();
+    assert_eq!(verified.join_async().await.unwrap(), 1234);
+
+    let tampered = thread::Builder::new()
+        .script_integrity("sha256-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".into())
+        .spawn(|| 1234)
+        .unwrap();
+    let err = tampered.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::IntegrityCheckFailed)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_handle_abort() {
+    let running = thread::spawn(|| loop {
+        thread::sleep(Duration::from_millis(10));
+    });
+    sleep_async(Duration::from_millis(50)).await;
+    running.abort();
+    let err = running.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Aborted)
+    );
+
+    // Aborting a finished thread keeps its result
+    let finished = thread::spawn(|| 1234);
+    finished.detach_wait().wait().await;
+    finished.abort();
+    assert_eq!(finished.join_async().await.unwrap(), 1234);
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_here() {
+    fn worker_name() -> Option<String> {
+        js_sys::Reflect::get(&js_sys::global(), &"name".into())
+            .unwrap()
+            .as_string()
+    }
+
+    let (handle, expected) = (thread::spawn_here!(worker_name), format!("{}:{}", file!(), line!()));
+    assert_eq!(handle.thread().name(), Some(expected.as_str()));
+    assert_eq!(handle.join_async().await.unwrap(), Some(expected));
+
+    let (handle, expected) = (
+        thread::spawn_here!(thread::Builder::new().prefix("pool".into()), worker_name),
+        format!("pool:{}:{}", file!(), line!() - 1),
+    );
+    assert_eq!(handle.join_async().await.unwrap(), Some(expected));
+
+    // An explicit name is kept
+    let handle = thread::Builder::new()
+        .name("named".into())
+        .spawn_here(worker_name)
+        .unwrap();
+    assert_eq!(handle.join_async().await.unwrap().as_deref(), Some("named"));
+}
+
+#[wasm_bindgen_test]
+async fn thread_captured_value_panics_on_drop() {
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("captured value panicked on drop");
+        }
+    }
+
+    let captured = PanicOnDrop;
+    let (mut panics, handle) = thread::Builder::new()
+        .panic_channel(move || {
+            // Only borrowed, so the value is dropped with the closure once it has returned
+            let _ = &captured;
+            1234
+        })
+        .unwrap();
+
+    let report = panics.next().await.unwrap();
+    assert_eq!(report.message(), "captured value panicked on drop");
+
+    // Reported as a panic of the thread, which traps with `panic=abort`
+    let err = handle.join_async().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<thread::Termination>(),
+        Some(&thread::Termination::Trapped)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_lazy() {
+    let spawned = thread::stats().total_spawned();
+
+    let mut handles: Vec<_> = (0..4)
+        .map(|i| thread::Builder::new().spawn_lazy(move || i * 2))
+        .collect();
+    assert_eq!(thread::stats().total_spawned(), spawned);
+
+    handles[1].start().unwrap();
+    assert!(handles[1].is_started());
+    assert!(!handles[2].is_started());
+    assert_eq!(thread::stats().total_spawned(), spawned + 1);
+
+    // Awaiting starts the thread too
+    let unstarted = handles.split_off(2);
+    let started = handles.pop().unwrap();
+    assert_eq!(started.await.unwrap(), 2);
+    assert_eq!(unstarted.into_iter().next().unwrap().await.unwrap(), 4);
+    assert_eq!(thread::stats().total_spawned(), spawned + 2);
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_batch_report() {
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let their_running = running.clone();
+    let their_peak = peak.clone();
+    let handles = thread::Builder::new()
+        .spawn_batch(3, move || {
+            let now_running = their_running.fetch_add(1, Ordering::SeqCst) + 1;
+            their_peak.fetch_max(now_running, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(200));
+            their_running.fetch_sub(1, Ordering::SeqCst);
+            thread::current_worker_index().unwrap()
+        })
+        .unwrap();
+
+    let (results, report) = thread::join_batch(handles).await;
+    let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(results, vec![0, 1, 2]);
+
+    assert_eq!(report.total_tasks(), 3);
+    assert_eq!(report.peak_concurrency(), peak.load(Ordering::SeqCst));
+    assert!(report.makespan().unwrap() >= 200.0);
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_after_shutdown() {
+    thread::shutdown();
+    let error = thread::Builder::new().spawn(|| ()).unwrap_err();
+    assert_eq!(error.to_string(), "crate is shutting down");
+
+    thread::restart();
+    thread::Builder::new().spawn(|| ()).unwrap().join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_spawn_with_ports() {
+    let channels = [
+        web_sys::MessageChannel::new().unwrap(),
+        web_sys::MessageChannel::new().unwrap(),
+    ];
+
+    // Resolves with the first message received on each of the ports kept by the main thread
+    let received: Vec<_> = channels
+        .iter()
+        .map(|channel| {
+            let port = channel.port2();
+            JsFuture::from(js_sys::Promise::new(&mut |resolve, _| {
+                let on_message = wasm_bindgen::closure::Closure::once_into_js(move |event: web_sys::MessageEvent| {
+                    resolve.call1(&JsValue::NULL, &event.data()).unwrap();
+                });
+                port.set_onmessage(Some(on_message.unchecked_ref()));
+            }))
+        })
+        .collect();
+
+    let ports = channels.iter().map(|channel| channel.port1()).collect();
+    let handle = thread::Builder::new()
+        .spawn_with_ports(ports, |ports| {
+            for (i, port) in ports.iter().enumerate() {
+                port.post_message(&JsValue::from(i as u32 * 10)).unwrap();
+            }
+            ports.len()
+        })
+        .unwrap();
+    assert_eq!(handle.join_async().await.unwrap(), 2);
+
+    for (i, message) in received.into_iter().enumerate() {
+        assert_eq!(message.await.unwrap().as_f64(), Some(i as f64 * 10.0));
+    }
+}
+
+#[wasm_bindgen_test]
+fn thread_spawn_with_duplicate_ports() {
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let result = thread::Builder::new().spawn_with_ports(vec![channel.port1(), channel.port1()], |_| ());
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(feature = "spawn_burst_detection")]
+#[wasm_bindgen_test]
+async fn thread_spawn_burst_warning() {
+    // Record console warnings while spawning
+    let console = js_sys::Reflect::get(&js_sys::global(), &"console".into()).unwrap();
+    let warn = js_sys::Reflect::get(&console, &"warn".into()).unwrap();
+    let warnings = js_sys::Array::new();
+    let record = js_sys::Function::new_with_args("message", "this.push(message)").bind0(&warnings);
+    js_sys::Reflect::set(&console, &"warn".into(), &record).unwrap();
+
+    let handles: Vec<_> = (0..20).map(|_| thread::spawn(|| ())).collect();
+    js_sys::Reflect::set(&console, &"warn".into(), &warn).unwrap();
+
+    assert!(warnings.iter().any(|warning| warning
+        .as_string()
+        .map_or(false, |warning| warning.contains("spawned in a single task"))));
+    for handle in handles {
+        handle.join_async().await.unwrap();
+    }
+}
+
+#[wasm_bindgen_test]
+async fn thread_float_ring() {
+    let (mut sink, stream) = thread::FloatRingSink::new(4096);
+
+    let handle = thread::spawn(move || {
+        let samples: Vec<f32> = (0..100_000).map(|i| i as f32).collect();
+        for chunk in samples.chunks(1000) {
+            assert!(sink.write(chunk));
+        }
+    });
+
+    let chunks: Vec<Vec<f32>> = stream.collect().await;
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 4096));
+    let samples: Vec<f32> = chunks.into_iter().flatten().collect();
+    assert_eq!(samples.len(), 100_000);
+    assert!(samples.iter().enumerate().all(|(i, &sample)| sample == i as f32));
+
+    handle.join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_float_ring_cursor_wraparound() {
+    // A capacity that does not divide 2^32, with the cursors starting just before `u32::MAX`
+    let (mut sink, stream) = thread::FloatRingSink::with_cursor(3, u32::MAX - 4);
+
+    let handle = thread::spawn(move || {
+        let samples: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+        for chunk in samples.chunks(7) {
+            assert!(sink.write(chunk));
+        }
+    });
+
+    let samples: Vec<f32> = stream.collect::<Vec<_>>().await.into_iter().flatten().collect();
+    assert_eq!(samples.len(), 10_000);
+    assert!(samples.iter().enumerate().all(|(i, &sample)| sample == i as f32));
+
+    handle.join_async().await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn thread_join_or_default() {
+    let handle = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(200));
+        1234
+    });
+    let (result, handle) = handle.join_or_default(Duration::from_millis(10), 0).await;
+    assert_eq!(result.unwrap(), 0);
+    // The thread kept running in the background
+    assert_eq!(handle.unwrap().join_async().await.unwrap(), 1234);
+
+    let handle = thread::spawn(|| 1234);
+    let (result, handle) = handle.join_or_default(Duration::from_secs(10), 0).await;
+    assert_eq!(result.unwrap(), 1234);
+    assert!(handle.is_none());
+}